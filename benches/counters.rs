@@ -0,0 +1,56 @@
+//
+// Throughput of concurrent writes and reads: `SharedSdm` (sharded locks) against
+// `AtomicSdm` (lock-free counters).
+//
+//     cargo bench --bench counters [-- <patterns per thread>]
+//
+extern crate kanerva;
+extern crate bittable;
+
+use std::env;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bittable::BitTable;
+use kanerva::address_space::AddressSpace;
+use kanerva::counter::Counter;
+use kanerva::shared::{AtomicSdm, SharedSdm};
+use kanerva::vsa;
+use kanerva::SDM;
+
+const RADIUS: usize = 54;
+
+fn seconds(d: Duration) -> f64 { d.as_secs() as f64 + f64::from(d.subsec_nanos()) / 1e9 }
+
+fn sdm() -> SDM { SDM::from_parts(AddressSpace::with_seed(1), Counter::new()) }
+
+/// Run `threads` threads each calling `op` on `per_thread` patterns; returns operations per second.
+fn run<M, F>(memory: Arc<M>, threads: u64, per_thread: u64, op: F) -> f64
+    where M: Send + Sync + 'static, F: Fn(&M, &BitTable) + Send + Sync + Copy + 'static
+{
+    let patterns: Arc<Vec<Vec<BitTable>>> = Arc::new((0..threads)
+        .map(|t| (0..per_thread).map(|k| vsa::random(t * per_thread + k)).collect())
+        .collect());
+    let start = Instant::now();
+    let handles: Vec<_> = (0..threads as usize).map(|t| {
+        let (memory, patterns) = (memory.clone(), patterns.clone());
+        thread::spawn(move || for p in patterns[t].iter() { op(&memory, p); })
+    }).collect();
+    for h in handles { h.join().unwrap(); }
+    (threads * per_thread) as f64 / seconds(start.elapsed())
+}
+
+fn main() {
+    let per_thread: u64 = env::args().skip(1).find(|a| !a.starts_with('-')).and_then(|a| a.parse().ok()).unwrap_or(2000);
+    println!("{:>8} {:>16} {:>16} {:>16} {:>16}", "threads", "sharded write/s", "atomic write/s", "sharded read/s", "atomic read/s");
+    for &threads in [1u64, 2, 4, 8].iter() {
+        let sharded = Arc::new(SharedSdm::new(sdm()));
+        let atomic = Arc::new(AtomicSdm::new(sdm()));
+        let sw = run(sharded.clone(), threads, per_thread, |m: &SharedSdm, p| m.write(p, RADIUS));
+        let aw = run(atomic.clone(), threads, per_thread, |m: &AtomicSdm, p| m.write(p, RADIUS));
+        let sr = run(sharded, threads, per_thread, |m: &SharedSdm, p| { m.read(p, RADIUS); });
+        let ar = run(atomic, threads, per_thread, |m: &AtomicSdm, p| { m.read(p, RADIUS); });
+        println!("{:>8} {:>16.0} {:>16.0} {:>16.0} {:>16.0}", threads, sw, aw, sr, ar);
+    }
+}
@@ -5,7 +5,7 @@ use std::error::Error;
 use std::ffi::OsString;
 use std::process::Command;
 use std::path::Path;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
 ///
 /// Build dimensions.
@@ -32,4 +32,113 @@ fn main() {
     write!(&mut f, "const ADD_DIM: usize = {};\n", addr_dim).expect("Could not write file");
     write!(&mut f, "#[allow(missing_docs)]\n").expect("Could not write file");
     write!(&mut f, "const DIM: usize = {};\n", dim).expect("Could not write file");
+
+    // `array::BoolArray` holds one `bool` per bit of an address: `DIM` words.
+    let word_bits: usize = env::var("CARGO_CFG_TARGET_POINTER_WIDTH").ok().and_then(|w| w.parse().ok()).unwrap_or(64);
+    let array_path = Path::new(&out_dir).join("array_constants.rs");
+    let mut f = File::create(&array_path).expect("Could not create file");
+    write!(&mut f, "#[allow(missing_docs)]\n").expect("Could not write file");
+    write!(&mut f, "const DIM: usize = {};\n", dim * word_bits).expect("Could not write file");
+
+    // Compile the C bitstring library, linked by `bitstring::ffi`.
+    println!("cargo:rerun-if-changed=src/bitstring.c");
+    println!("cargo:rerun-if-changed=src/bitstring.h");
+    println!("cargo:rerun-if-changed=src/lib/base64.c");
+    cc::Build::new()
+        .file("src/bitstring.c")
+        .file("src/lib/base64.c")
+        .include("src")
+        .define("SDM_USE_BUILTIN_POPCOUNT", None)
+        .compile("bitstring");
+
+    // Generate the C header of `capi` into `OUT_DIR`; `tests/capi.rs` checks that
+    // `include/kanerva.h` is the same, and updates it with `KANERVA_BLESS=1`.
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    let header = c_header(&fs::read_to_string("src/capi.rs").expect("Could not read src/capi.rs"));
+    fs::write(Path::new(&out_dir).join("kanerva.h"), &header).expect("Could not write file");
+}
+///
+/// C spelling of a type of the `extern "C"` functions of `capi`.
+///
+fn c_type(rust: &str) -> String {
+    let rust = rust.trim();
+    if let Some(pointee) = rust.strip_prefix("*const ") { return format!("const {} *", c_type(pointee)); }
+    if let Some(pointee) = rust.strip_prefix("*mut ") {
+        let inner = c_type(pointee);
+        return if inner.ends_with('*') { format!("{}*", inner) } else { format!("{} *", inner) };
+    }
+    match rust {
+        "size_t" => "size_t",
+        "u8" => "uint8_t",
+        "u64" => "uint64_t",
+        "c_int" => "int",
+        "c_char" => "char",
+        "KanervaSdm" => "kanerva_sdm",
+        _ => panic!("no C type for `{}` in src/capi.rs", rust),
+    }.to_string()
+}
+///
+/// C declaration `ty name`, without a space after a pointer.
+///
+fn c_decl(ty: &str, name: &str) -> String {
+    if ty.ends_with('*') { format!("{}{}", ty, name) } else { format!("{} {}", ty, name) }
+}
+///
+/// Header declaring the constants, handle and functions of `capi`, each under its doc comment.
+///
+fn c_header(source: &str) -> String {
+    let mut h = String::new();
+    h.push_str("/*\n");
+    h.push_str(" * kanerva.h: C API of the kanerva sparse distributed memory.\n");
+    h.push_str(" *\n");
+    h.push_str(" * Generated from `src/capi.rs` by `build.rs`; do not edit.\n");
+    h.push_str(" *\n");
+    h.push_str(" * Link with `libkanerva.a` and the system libraries it needs, e.g. on Linux:\n");
+    h.push_str(" *\n");
+    h.push_str(" *     cc app.c -Iinclude target/release/libkanerva.a -lpthread -ldl -lm\n");
+    h.push_str(" *\n");
+    h.push_str(" * Addresses and data are `kanerva_address_bytes()` bytes long:\n");
+    h.push_str(" * bit `i` is bit `7 - i % 8` of byte `i / 8`.\n");
+    h.push_str(" *\n");
+    h.push_str(" * Functions returning `int` return `KANERVA_OK` or an error code.\n");
+    h.push_str(" */\n");
+    h.push_str("#ifndef KANERVA_H\n#define KANERVA_H\n\n");
+    h.push_str("#include <stddef.h>\n#include <stdint.h>\n\n");
+    h.push_str("#ifdef __cplusplus\nextern \"C\" {\n#endif\n\n");
+    let mut doc: Vec<&str> = Vec::new();
+    for line in source.lines() {
+        // Items of the test module are indented and never reach the header.
+        if let Some(text) = line.strip_prefix("///") {
+            let text = text.trim();
+            if !text.is_empty() { doc.push(text); }
+            continue;
+        }
+        if line.starts_with("#[") { continue; }
+        let comment = format!("/* {} */\n", doc.join(" "));
+        if line.starts_with("pub const KANERVA_") {
+            let name = line["pub const ".len()..line.find(':').unwrap()].trim();
+            let value = line[line.find('=').unwrap() + 1..].trim().trim_end_matches(';');
+            h.push_str(&comment);
+            h.push_str(&format!("#define {} {}\n", name, value));
+        } else if line.starts_with("pub struct KanervaSdm") {
+            h.push_str(&format!("\n{}typedef struct KanervaSdm kanerva_sdm;\n\n", comment));
+        } else if let Some(rest) = line.split("extern \"C\" fn ").nth(1).filter(|_| line.starts_with("pub ")) {
+            let name = &rest[..rest.find('(').unwrap()];
+            let args = &rest[rest.find('(').unwrap() + 1..rest.find(')').unwrap()];
+            let ret = rest[rest.find(')').unwrap() + 1..].split('{').next().unwrap().trim();
+            let ret = ret.strip_prefix("->").map_or("void".to_string(), c_type);
+            let args: Vec<String> = args.split(',').map(str::trim).filter(|a| !a.is_empty())
+                .map(|a| {
+                    let colon = a.find(':').unwrap();
+                    c_decl(&c_type(&a[colon + 1..]), a[..colon].trim())
+                })
+                .collect();
+            let args = if args.is_empty() { "void".to_string() } else { args.join(", ") };
+            h.push_str(&comment);
+            h.push_str(&format!("{}({});\n", c_decl(&ret, name), args));
+        }
+        doc.clear();
+    }
+    h.push_str("\n#ifdef __cplusplus\n}\n#endif\n\n#endif /* KANERVA_H */\n");
+    h
 }
\ No newline at end of file
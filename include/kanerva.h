@@ -0,0 +1,70 @@
+/*
+ * kanerva.h: C API of the kanerva sparse distributed memory.
+ *
+ * Generated from `src/capi.rs` by `build.rs`; do not edit.
+ *
+ * Link with `libkanerva.a` and the system libraries it needs, e.g. on Linux:
+ *
+ *     cc app.c -Iinclude target/release/libkanerva.a -lpthread -ldl -lm
+ *
+ * Addresses and data are `kanerva_address_bytes()` bytes long:
+ * bit `i` is bit `7 - i % 8` of byte `i / 8`.
+ *
+ * Functions returning `int` return `KANERVA_OK` or an error code.
+ */
+#ifndef KANERVA_H
+#define KANERVA_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Success. */
+#define KANERVA_OK 0
+/* A required pointer is `NULL`. */
+#define KANERVA_ERR_NULL 1
+/* A buffer length is not `kanerva_address_bytes()`. */
+#define KANERVA_ERR_LENGTH 2
+/* The file could not be opened, read or written. */
+#define KANERVA_ERR_IO 3
+/* The file is not a memory of this build. */
+#define KANERVA_ERR_FORMAT 4
+/* The path is not valid UTF-8. */
+#define KANERVA_ERR_PATH 5
+/* Internal error. */
+#define KANERVA_ERR_PANIC 6
+
+/* Opaque handle of the C API. */
+typedef struct KanervaSdm kanerva_sdm;
+
+/* Number of bits of an address. */
+size_t kanerva_address_bits(void);
+/* Number of bytes of an address buffer. */
+size_t kanerva_address_bytes(void);
+/* Number of hard locations. */
+size_t kanerva_hard_locations(void);
+/* A new empty memory drawn from `seed`; `radius` `0` picks the default radius. Returns `NULL` on failure. */
+kanerva_sdm *kanerva_sdm_new(uint64_t seed, size_t radius);
+/* Release a memory; `NULL` is ignored. */
+void kanerva_sdm_free(kanerva_sdm *sdm);
+/* Activation radius of the memory, `0` for `NULL`. */
+size_t kanerva_sdm_radius(const kanerva_sdm *sdm);
+/* Write `data` at `address`, both of `len` bytes. */
+int kanerva_sdm_write(kanerva_sdm *sdm, const uint8_t *address, const uint8_t *data, size_t len);
+/* Read at `cue` into `out`, both of `len` bytes. */
+int kanerva_sdm_read(kanerva_sdm *sdm, const uint8_t *cue, uint8_t *out, size_t len);
+/* Save the memory to `path`. */
+int kanerva_sdm_save(const kanerva_sdm *sdm, const char *p);
+/* Load the memory saved at `path` into `*out`. */
+int kanerva_sdm_load(const char *p, kanerva_sdm **out);
+/* Static description of an error code. */
+const char *kanerva_strerror(int code);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* KANERVA_H */
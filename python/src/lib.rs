@@ -0,0 +1,242 @@
+//
+// Python extension module `kanerva`, built with `maturin build` (or `maturin develop`).
+//
+// Addresses and data are NumPy arrays in one of two layouts:
+//
+//     bool  `(bits,)`       one element per bit
+//     uint8 `(bytes,)`      packed bits, bit `i` in bit `7 - i % 8` of byte `i / 8`,
+//                           as produced by `numpy.packbits`
+//
+// A 2-D array, one pattern per row, writes or reads a whole batch in one call, without
+// holding the GIL. Results are returned in the layout and shape of the cue.
+//
+///
+use std::path::PathBuf;
+///
+use bittable::BitTable;
+///
+use kanerva::analysis;
+///
+use kanerva::encoding::{address_bytes, from_bytes, to_bytes};
+///
+use kanerva::store::{self, Memory};
+///
+use kanerva::vsa;
+///
+use numpy::ndarray::Array2;
+///
+use numpy::{IntoPyArray, PyReadonlyArray1, PyReadonlyArray2};
+///
+use pyo3::exceptions::{PyIOError, PyTypeError, PyValueError};
+///
+use pyo3::prelude::*;
+///
+/// Layout of the arrays of a call.
+///
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Layout {
+    /// `bool`, one element per bit.
+    Bits,
+    /// `uint8`, packed bits.
+    Packed,
+}
+///
+/// Patterns of an array, with the layout and whether the array was 2-D.
+///
+#[derive(Debug)]
+struct Patterns {
+    rows: Vec<BitTable>,
+    layout: Layout,
+    batch: bool,
+}
+///
+///
+///
+fn bits() -> usize { BitTable::new().len() }
+///
+///
+///
+fn from_bits(row: &[bool]) -> PyResult<BitTable> {
+    if row.len() != bits() {
+        return Err(PyValueError::new_err(format!("expected {} bits, found {}", bits(), row.len())));
+    }
+    Ok(BitTable::from_slice(row))
+}
+///
+///
+///
+fn from_packed(row: &[u8]) -> PyResult<BitTable> {
+    from_bytes(row).map_err(|_| PyValueError::new_err(format!("expected {} bytes, found {}", address_bytes(), row.len())))
+}
+///
+/// Read the patterns of a `bool` or `uint8` array of 1 or 2 dimensions.
+///
+fn patterns(array: &Bound<PyAny>) -> PyResult<Patterns> {
+    if let Ok(a) = array.extract::<PyReadonlyArray2<bool>>() {
+        let rows = a.as_array().outer_iter().map(|r| from_bits(&r.to_vec())).collect::<PyResult<_>>()?;
+        return Ok(Patterns { rows, layout: Layout::Bits, batch: true });
+    }
+    if let Ok(a) = array.extract::<PyReadonlyArray2<u8>>() {
+        let rows = a.as_array().outer_iter().map(|r| from_packed(&r.to_vec())).collect::<PyResult<_>>()?;
+        return Ok(Patterns { rows, layout: Layout::Packed, batch: true });
+    }
+    if let Ok(a) = array.extract::<PyReadonlyArray1<bool>>() {
+        let rows = vec![from_bits(&a.as_array().to_vec())?];
+        return Ok(Patterns { rows, layout: Layout::Bits, batch: false });
+    }
+    if let Ok(a) = array.extract::<PyReadonlyArray1<u8>>() {
+        let rows = vec![from_packed(&a.as_array().to_vec())?];
+        return Ok(Patterns { rows, layout: Layout::Packed, batch: false });
+    }
+    Err(PyTypeError::new_err("expected a 1-D or 2-D NumPy array of bool or uint8"))
+}
+///
+/// Addresses and data of a write; the data defaults to the addresses.
+///
+fn pairs(address: &Bound<PyAny>, data: Option<&Bound<PyAny>>) -> PyResult<(Vec<BitTable>, Vec<BitTable>)> {
+    let a = patterns(address)?;
+    let d = match data {
+        Some(d) => patterns(d)?.rows,
+        None => a.rows.clone(),
+    };
+    if d.len() != a.rows.len() {
+        return Err(PyValueError::new_err(format!("{} addresses but {} data patterns", a.rows.len(), d.len())));
+    }
+    Ok((a.rows, d))
+}
+///
+/// An array of `rows` in `layout`, 2-D if `batch`.
+///
+fn array<'py>(py: Python<'py>, rows: &[BitTable], layout: Layout, batch: bool) -> PyResult<Bound<'py, PyAny>> {
+    let out = match layout {
+        Layout::Bits => {
+            let flat: Vec<bool> = rows.iter().flat_map(|r| (0..r.len()).map(move |i| r.get(i))).collect();
+            let a = Array2::from_shape_vec((rows.len(), bits()), flat).expect("row length");
+            if batch { a.into_pyarray(py).into_any() } else { a.row(0).to_owned().into_pyarray(py).into_any() }
+        },
+        Layout::Packed => {
+            let flat: Vec<u8> = rows.iter().flat_map(|r| to_bytes(r)).collect();
+            let a = Array2::from_shape_vec((rows.len(), address_bytes()), flat).expect("row length");
+            if batch { a.into_pyarray(py).into_any() } else { a.row(0).to_owned().into_pyarray(py).into_any() }
+        },
+    };
+    Ok(out)
+}
+///
+/// A sparse distributed memory, as saved by the `kanerva` command-line tool.
+///
+#[pyclass(name = "SDM", module = "kanerva")]
+#[derive(Debug)]
+struct Sdm {
+    memory: Memory,
+}
+///
+///
+///
+#[pymethods]
+impl Sdm {
+    ///
+    /// An empty memory with hard locations drawn from `seed`; the default radius
+    /// activates about the square root of the number of hard locations.
+    ///
+    #[new]
+    #[pyo3(signature = (seed = 0, radius = None))]
+    fn new(seed: u64, radius: Option<usize>) -> Self {
+        let r = radius.unwrap_or_else(|| analysis::default_radius(bits(), store::hard_locations()));
+        Sdm { memory: Memory::new(seed, r) }
+    }
+    ///
+    ///
+    ///
+    #[getter]
+    fn seed(&self) -> u64 { self.memory.seed() }
+    ///
+    ///
+    ///
+    #[getter]
+    fn radius(&self) -> usize { self.memory.radius() }
+    ///
+    ///
+    ///
+    #[setter]
+    fn set_radius(&mut self, radius: usize) { self.memory.set_radius(radius); }
+    ///
+    /// Number of patterns written.
+    ///
+    #[getter]
+    fn writes(&self) -> u64 { self.memory.writes() }
+    ///
+    /// Write `data` at `address`, or `address` at itself; 2-D arrays write one pattern
+    /// per row. Returns the number of patterns written.
+    ///
+    #[pyo3(signature = (address, data = None))]
+    fn write(&mut self, py: Python, address: &Bound<PyAny>, data: Option<&Bound<PyAny>>) -> PyResult<usize> {
+        let (a, d) = pairs(address, data)?;
+        let memory = &mut self.memory;
+        py.detach(|| for (x, y) in a.iter().zip(d.iter()) { memory.write(x, y); });
+        Ok(a.len())
+    }
+    ///
+    /// Undo a previous `write(address, data)`.
+    ///
+    #[pyo3(signature = (address, data = None))]
+    fn erase(&mut self, py: Python, address: &Bound<PyAny>, data: Option<&Bound<PyAny>>) -> PyResult<usize> {
+        let (a, d) = pairs(address, data)?;
+        let memory = &mut self.memory;
+        py.detach(|| for (x, y) in a.iter().zip(d.iter()) { memory.erase(x, y); });
+        Ok(a.len())
+    }
+    ///
+    /// Read at `cue`; a 2-D array reads one cue per row.
+    ///
+    fn read<'py>(&self, py: Python<'py>, cue: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
+        let cues = patterns(cue)?;
+        let memory = &self.memory;
+        let rows: Vec<BitTable> = py.detach(|| cues.rows.iter().map(|c| memory.read(c)).collect());
+        array(py, &rows, cues.layout, cues.batch)
+    }
+    ///
+    /// Erase everything written, keeping the hard locations.
+    ///
+    fn clear(&mut self) { self.memory.clear(); }
+    ///
+    /// Save the memory in the format of the `kanerva` command-line tool.
+    ///
+    fn save(&self, path: PathBuf) -> PyResult<()> {
+        self.memory.save(&path).map_err(|e| PyIOError::new_err(format!("{}: {}", path.display(), e)))
+    }
+    ///
+    /// Load a memory saved by `save` or by the `kanerva` command-line tool.
+    ///
+    #[staticmethod]
+    fn load(path: PathBuf) -> PyResult<Self> {
+        Memory::load(&path)
+            .map(|memory| Sdm { memory })
+            .map_err(|e| PyIOError::new_err(format!("{}: {}", path.display(), e)))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("SDM(seed={}, radius={}, writes={})", self.memory.seed(), self.memory.radius(), self.memory.writes())
+    }
+}
+///
+/// A random address drawn from `seed`, in the layout given by `packed`.
+///
+#[pyfunction]
+#[pyo3(signature = (seed, packed = false))]
+fn random(py: Python, seed: u64, packed: bool) -> PyResult<Bound<PyAny>> {
+    array(py, &[vsa::random(seed)], if packed { Layout::Packed } else { Layout::Bits }, false)
+}
+///
+///
+///
+#[pymodule]
+#[pyo3(name = "kanerva")]
+fn kanerva_module(m: &Bound<PyModule>) -> PyResult<()> {
+    m.add("BITS", bits())?;
+    m.add("BYTES", address_bytes())?;
+    m.add("HARD_LOCATIONS", store::hard_locations())?;
+    m.add_class::<Sdm>()?;
+    m.add_function(wrap_pyfunction!(random, m)?)?;
+    Ok(())
+}
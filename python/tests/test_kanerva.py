@@ -0,0 +1,72 @@
+#
+# Tests of the Python extension module, run with `pytest python/tests` after
+# `maturin develop -m python/Cargo.toml`.
+#
+import numpy as np
+import pytest
+
+import kanerva
+
+
+def test_layouts():
+    bits = kanerva.random(1)
+    packed = kanerva.random(1, packed=True)
+    assert bits.dtype == np.bool_ and bits.shape == (kanerva.BITS,)
+    assert packed.dtype == np.uint8 and packed.shape == (kanerva.BYTES,)
+    assert np.array_equal(np.packbits(bits), packed)
+
+
+def test_write_read():
+    m = kanerva.SDM(seed=1)
+    x = kanerva.random(2)
+    assert m.write(x) == 1
+    assert m.writes == 1
+    assert np.array_equal(m.read(x), x)
+    assert np.array_equal(m.read(np.packbits(x)), np.packbits(x))
+
+
+def test_batch():
+    m = kanerva.SDM(seed=2)
+    xs = np.stack([kanerva.random(k) for k in range(10)])
+    assert m.write(xs) == 10
+    noisy = xs.copy()
+    noisy[:, :5] ^= True
+    out = m.read(noisy)
+    assert out.shape == xs.shape
+    assert np.array_equal(out, xs)
+    packed = m.read(np.packbits(xs, axis=1))
+    assert np.array_equal(packed, np.packbits(xs, axis=1))
+
+
+def test_erase_clear():
+    m = kanerva.SDM(seed=3)
+    x, y = kanerva.random(4), kanerva.random(5)
+    m.write(x, y)
+    m.erase(x, y)
+    assert m.writes == 0
+    m.write(x)
+    m.clear()
+    assert m.writes == 0
+
+
+def test_save_load(tmp_path):
+    m = kanerva.SDM(seed=4, radius=50)
+    x = kanerva.random(6)
+    m.write(x)
+    path = tmp_path / "m.sdm"
+    m.save(str(path))
+    n = kanerva.SDM.load(str(path))
+    assert (n.seed, n.radius, n.writes) == (4, 50, 1)
+    assert np.array_equal(n.read(x), x)
+
+
+def test_errors(tmp_path):
+    m = kanerva.SDM()
+    with pytest.raises(ValueError):
+        m.write(np.zeros(kanerva.BITS - 1, dtype=bool))
+    with pytest.raises(ValueError):
+        m.write(np.zeros((2, kanerva.BITS), dtype=bool), np.zeros((3, kanerva.BITS), dtype=bool))
+    with pytest.raises(TypeError):
+        m.read(np.zeros(kanerva.BITS, dtype=np.float64))
+    with pytest.raises(OSError):
+        kanerva.SDM.load(str(tmp_path / "missing.sdm"))
@@ -5,8 +5,14 @@
 #[allow(missing_docs)]
 include!(concat!(env!("OUT_DIR"), "/dimensions.rs"));
 ///
+use rand::Rng;
+///
 use bittable::BitTable;
 ///
+use noise::rng_from_seed;
+///
+use hypervector::HyperVector;
+///
 ///
 /// Space of addresses.
 ///
@@ -29,7 +35,7 @@ use bittable::BitTable;
 ///
 ///
 #[derive(Clone, Debug)]
-pub struct AddressSpace (Vec<BitTable>);
+pub struct AddressSpace<V = BitTable> (Vec<V>);
 ///
 ///
 ///
@@ -47,41 +53,14 @@ impl AddressSpace {
         for _ in 0..ADD_DIM {
             vec.push(BitTable::new_rand());
         }
-
-        let mut counter: usize = 0;
-        let mut d: usize;
-
-        for i in 0..ADD_DIM {
-            println!("vec[{}].ones()={}", i, vec[i].ones()); 
-        }
-        println!("vec.len()={}", vec.len()); 
-
-        let mut b=BitTable::new_rand();
-
-        println!(); 
-        for i in 0..ADD_DIM { 
-            d = b.distance(&vec[i]);
-            println!("i={}:d={}", i, d); 
-            if d < 128 { counter += 1; }
-        }
-        println!(" << distance = {} =================================", counter);
-        println!(); 
         AddressSpace(vec)
 	}
-	///
-	///
-	///
-	pub fn len(&self) -> usize { self.0.len() }
     ///
+    /// A random address space drawn from `seed`: the same seed gives the same hard locations.
     ///
-    /// 
-    pub fn get(& self, i: usize) -> &BitTable { &self.0[i] }   
-	///
-	///
-	///	
-	pub fn set(&mut self, i: usize, bt: &BitTable) {
-        self.0[i] = bt.clone();
-	}
+    pub fn with_seed(seed: u64) -> Self {
+        AddressSpace::seeded(BitTable::new().len(), seed)
+    }
     ///
     /// 
     ///
@@ -119,18 +98,6 @@ impl AddressSpace {
         counter
     }
     ///
-    /// 
-    ///
-    pub fn radius_fit(&self, bt: &BitTable, radius: usize) -> Vec<usize> {
-        
-        let mut selected: Vec<usize> = vec![0;ADD_DIM];
-        for i in 0..ADD_DIM {  
-            let x: BitTable = self.0[i] ^ *bt;
-            if x.zeros() <= radius { selected[i]+=1; }
-        }
-        selected
-    }
-    ///
     ///
     ///
     pub fn xor_in_place<'a>(&self, left: &'a [bool], right: &'a [bool]) -> Vec<bool> {
@@ -139,7 +106,62 @@ impl AddressSpace {
         let it = right.iter().cycle();
         left.iter().zip(it).map(|(&a, b)| a ^ b ).collect()
     }
-
+}
+///
+/// Address spaces of any representation of the addresses.
+///
+impl<V: HyperVector> AddressSpace<V> {
+    ///
+    /// `ADD_DIM` random addresses of `bits` bits.
+    ///
+    pub fn random(bits: usize) -> Self {
+        AddressSpace((0..ADD_DIM).map(|_| V::random(bits)).collect())
+    }
+    ///
+    /// `ADD_DIM` addresses of `bits` bits drawn from `seed`, with the same bits in every
+    /// representation: the same seed gives the same hard locations.
+    ///
+    pub fn seeded(bits: usize, seed: u64) -> Self {
+        let mut rng = rng_from_seed(seed);
+        let mut vec: Vec<V> = Vec::with_capacity(ADD_DIM);
+        for _ in 0..ADD_DIM {
+            let v: Vec<bool> = (0..bits).map(|_| rng.gen()).collect();
+            vec.push(V::from_bools(&v));
+        }
+        AddressSpace(vec)
+    }
+    ///
+    /// Build an address space from `ADD_DIM` existing addresses.
+    ///
+    pub fn from_vec(vec: Vec<V>) -> Self {
+        assert_eq!(vec.len(), ADD_DIM);
+        AddressSpace(vec)
+    }
+	///
+	///
+	///
+	pub fn len(&self) -> usize { self.0.len() }
+    ///
+    ///
+    /// 
+    pub fn get(& self, i: usize) -> &V { &self.0[i] }   
+	///
+	///
+	///	
+	pub fn set(&mut self, i: usize, bt: &V) {
+        self.0[i] = bt.clone();
+	}
+    ///
+    /// 
+    ///
+    pub fn radius_fit(&self, bt: &V, radius: usize) -> Vec<usize> {
+        
+        let mut selected: Vec<usize> = vec![0;ADD_DIM];
+        for i in 0..ADD_DIM {  
+            if bt.distance(&self.0[i]) <= radius { selected[i]+=1; }
+        }
+        selected
+    }
 }
 
 #[cfg(test)]
@@ -157,6 +179,15 @@ mod tests {
         assert_eq!(a.len(), ADD_DIM);
     } 
 
+    #[test]
+    fn test_address_space_with_seed() {
+        let a = AddressSpace::with_seed(1);
+        let b = AddressSpace::with_seed(1);
+        assert_eq!(a.len(), ADD_DIM);
+        assert!((0..ADD_DIM).all(|i| a.get(i) == b.get(i)));
+        assert!(a.get(0) != AddressSpace::with_seed(2).get(0));
+    }
+
     #[test]
     fn test_address_space_radius_counter() {
         let a: AddressSpace = AddressSpace::new();
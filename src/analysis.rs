@@ -0,0 +1,271 @@
+//
+// Theoretical analysis of the SDM, following Kanerva (1988).
+//
+// Notation used throughout the module:
+//   `n`: dimension of the addresses, in bits.
+//   `m`: number of hard locations.
+//   `r`: activation radius.
+//   `d`: Hamming distance between two addresses.
+//   `t`: number of stored patterns.
+//
+///
+use std::f64::consts::SQRT_2;
+///
+/// Natural logarithms of `0!, 1!, ..., n!`.
+///
+fn ln_factorials(n: usize) -> Vec<f64> {
+    let mut v: Vec<f64> = vec![0.0; n+1];
+    for k in 1..(n+1) { v[k] = v[k-1] + (k as f64).ln(); }
+    v
+}
+///
+/// Cumulative distribution `P(X <= k)` for `X ~ Binomial(n, 1/2)`, for all `k` in `0..n+1`.
+///
+fn binomial_cdf(n: usize) -> Vec<f64> {
+    let lf = ln_factorials(n);
+    let ln2n = (n as f64) * 2f64.ln();
+    let mut cdf: Vec<f64> = vec![0.0; n+1];
+    let mut acc: f64 = 0.0;
+    for k in 0..(n+1) {
+        acc += (lf[n] - lf[k] - lf[n-k] - ln2n).exp();
+        cdf[k] = acc.min(1.0);
+    }
+    cdf
+}
+///
+/// Probability mass `P(X = k)` for `X ~ Binomial(n, 1/2)`.
+///
+fn binomial_pmf(lf: &[f64], n: usize, k: usize) -> f64 {
+    (lf[n] - lf[k] - lf[n-k] - (n as f64) * 2f64.ln()).exp()
+}
+///
+/// Standard normal cumulative distribution function.
+///
+/// Uses the complementary error function approximation of Numerical Recipes
+/// (fractional error below `1.2e-7`).
+///
+pub fn normal_cdf(x: f64) -> f64 {
+    let z = (x / SQRT_2).abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let erfc = t * (-z*z - 1.26551223 + t * (1.00002368 + t * (0.37409196 + t * (0.09678418
+        + t * (-0.18628806 + t * (0.27886807 + t * (-1.13520398 + t * (1.48851587
+        + t * (-0.82215223 + t * 0.17087277))))))))).exp();
+    if x >= 0.0 { 1.0 - 0.5 * erfc } else { 0.5 * erfc }
+}
+///
+/// Probability that a random hard location lies within radius `r` of an address,
+/// i.e. the probability that it is activated.
+///
+/// For `n = 1000` and `r = 451` this is about `0.001` (Kanerva, 1988).
+///
+pub fn activation_probability(n: usize, r: usize) -> f64 {
+    if r >= n { return 1.0; }
+    binomial_cdf(n)[r]
+}
+///
+/// Expected number of hard locations activated by an address.
+///
+pub fn expected_activated(n: usize, m: usize, r: usize) -> f64 {
+    (m as f64) * activation_probability(n, r)
+}
+///
+/// Probability that a random hard location is activated by two addresses `d` bits apart,
+/// i.e. lies in the intersection of the two circles of radius `r`.
+///
+/// A location differing from the first address in `a` of the `d` bits where the
+/// addresses disagree, and in `b` of the `n-d` bits where they agree, is at distance
+/// `a+b` from the first and `d-a+b` from the second address.
+///
+pub fn overlap_probability(n: usize, r: usize, d: usize) -> f64 {
+    assert!(d <= n, "distance {} exceeds dimension {}", d, n);
+    let lf = ln_factorials(n);
+    let cdf = binomial_cdf(n-d);
+    let mut p: f64 = 0.0;
+    for a in 0..(d+1) {
+        if a > r || d-a > r { continue; }
+        let bmax = (r-a).min(r-(d-a)).min(n-d);
+        p += binomial_pmf(&lf, d, a) * cdf[bmax];
+    }
+    p.min(1.0)
+}
+///
+/// Expected number of hard locations shared by the activation sets of two
+/// addresses `d` bits apart.
+///
+pub fn expected_overlap(n: usize, m: usize, r: usize, d: usize) -> f64 {
+    (m as f64) * overlap_probability(n, r, d)
+}
+///
+/// Variance of the sum read at one bit, at a written address, after `t` writes.
+///
+/// The pattern itself contributes `m*p`; each of the other `t-1` patterns shares
+/// about `m*p^2` locations with it, with variance `m*p^2*(1+m*p^2)`.
+///
+fn read_variance(m: usize, p: f64, t: usize) -> f64 {
+    let m = m as f64;
+    let others = if t > 0 { (t-1) as f64 } else { 0.0 };
+    m * p * (1.0 + p * others * (1.0 + p * p * m))
+}
+///
+/// Signal-to-noise ratio of a read at a written address, after `t` writes.
+///
+/// `rho = sqrt(m*p / (1 + p*(t-1)*(1 + p^2*m)))`, so that a single pattern
+/// is read with `rho = sqrt(m*p)`.
+///
+pub fn signal_to_noise(n: usize, m: usize, r: usize, t: usize) -> f64 {
+    let p = activation_probability(n, r);
+    (m as f64) * p / read_variance(m, p, t).sqrt()
+}
+///
+/// Probability that one bit is read wrongly at a written address, after `t` writes.
+///
+pub fn bit_error_probability(n: usize, m: usize, r: usize, t: usize) -> f64 {
+    normal_cdf(-signal_to_noise(n, m, r, t))
+}
+///
+/// Predicted capacity: the largest number of stored patterns for which the read
+/// at a written address keeps a signal-to-noise ratio of at least `rho`.
+///
+/// Returns `0` if even a single pattern falls below `rho`.
+///
+pub fn capacity(n: usize, m: usize, r: usize, rho: f64) -> usize {
+    capacity_at(m, activation_probability(n, r), rho)
+}
+///
+/// Predicted capacity as `capacity`, for an activation probability `p` of each hard
+/// location, such as one measured on the writes of a memory.
+///
+pub fn capacity_at(m: usize, p: f64, rho: f64) -> usize {
+    let mf = m as f64;
+    let single = mf * p;
+    if single < rho * rho { return 0; }
+    let t = 1.0 + (single / (rho * rho) - 1.0) / (p * (1.0 + p * p * mf));
+    t.floor() as usize
+}
+///
+/// Expected distance to the stored pattern after one read, with a cue `d` bits away
+/// from the written address, after `t` writes.
+///
+/// As in Kanerva's convergence analysis, each of the other `t-1` patterns shares
+/// about `m*p^2` locations with the read, independently of the others, so the noise
+/// leaves out the `p^2*m` correlation term of `signal_to_noise`.
+///
+pub fn next_distance(n: usize, m: usize, r: usize, t: usize, d: usize) -> f64 {
+    let p = activation_probability(n, r);
+    let signal = expected_overlap(n, m, r, d);
+    let others = if t > 0 { (t-1) as f64 } else { 0.0 };
+    let noise = ((m as f64) * p * (1.0 + p * others)).sqrt();
+    (n as f64) * normal_cdf(-signal / noise)
+}
+///
+/// Critical distance: the largest cue distance from which iterative reading still
+/// converges, i.e. for which one read brings the cue closer to the stored pattern.
+///
+/// Returns `0` if no cue, not even an exact one, is read back closer.
+///
+pub fn critical_distance(n: usize, m: usize, r: usize, t: usize) -> usize {
+    let mut critical: usize = 0;
+    for d in 1..(n/2+1) {
+        if next_distance(n, m, r, t, d) < d as f64 { critical = d; } else { break; }
+    }
+    critical
+}
+///
+/// Default activation radius: the smallest one activating about `sqrt(m)` hard locations.
+///
+pub fn default_radius(n: usize, m: usize) -> usize {
+    let target = (m as f64).sqrt();
+    (0..n+1).find(|&r| expected_activated(n, m, r) >= target).unwrap_or(n)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const N: usize = 1000;
+    const M: usize = 1_000_000;
+    const R: usize = 451;
+
+    #[test]
+    fn test_analysis_normal_cdf() {
+        assert!((normal_cdf(0.0) - 0.5).abs() < 1e-7);
+        assert!((normal_cdf(1.96) - 0.975).abs() < 1e-4);
+        assert!((normal_cdf(-1.96) - 0.025).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_analysis_activation_probability() {
+        let p = activation_probability(N, R);
+        assert!((p - 0.001).abs() < 1e-4, "p = {}", p);
+        assert_eq!(activation_probability(N, N), 1.0);
+        assert!((activation_probability(N, N/2) - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_analysis_expected_overlap() {
+        let a = expected_activated(N, M, R);
+        let o0 = expected_overlap(N, M, R, 0);
+        assert!((a - o0).abs() < 1e-6 * a);
+        let mut prev = o0;
+        for d in (50..N/2).filter(|d| d % 50 == 0) {
+            let o = expected_overlap(N, M, R, d);
+            assert!(o < prev, "overlap at {} = {} not below {}", d, o, prev);
+            prev = o;
+        }
+        // Two addresses at the indifference distance share about `m*p^2` locations.
+        let o = expected_overlap(N, M, R, N/2);
+        assert!(o < 10.0 * a * a / (M as f64));
+    }
+
+    #[test]
+    fn test_analysis_signal_to_noise() {
+        let p = activation_probability(N, R);
+        let s1 = signal_to_noise(N, M, R, 1);
+        assert!((s1 - ((M as f64) * p).sqrt()).abs() < 1e-9);
+        assert!(signal_to_noise(N, M, R, 10_000) < s1);
+        assert!(bit_error_probability(N, M, R, 10_000) < bit_error_probability(N, M, R, 100_000));
+    }
+
+    #[test]
+    fn test_analysis_capacity() {
+        let rho = 5.0;
+        let t = capacity(N, M, R, rho);
+        assert!(t > 0);
+        assert!(signal_to_noise(N, M, R, t) >= rho);
+        assert!(signal_to_noise(N, M, R, t+1) < rho);
+        assert_eq!(capacity_at(M, activation_probability(N, R), rho), t);
+    }
+
+    #[test]
+    fn test_analysis_default_radius() {
+        assert_eq!(default_radius(128, 1000), 54);
+        let r = default_radius(N, M);
+        assert!(expected_activated(N, M, r) >= 1000.0);
+        assert!(expected_activated(N, M, r - 1) < 1000.0);
+    }
+
+    #[test]
+    fn test_analysis_book() {
+        // Kanerva (1988): n = 1000, M = 1,000,000 and r = 451 give p = 0.001, about
+        // 1000 activated locations, and a critical distance of about 209 bits once
+        // 10,000 patterns are stored.
+        assert!((expected_activated(N, M, R) - 1071.85).abs() < 0.01);
+        let c = critical_distance(N, M, R, 10_000);
+        assert!(c >= 204 && c <= 214, "critical distance {}", c);
+        // A single stored pattern is read with a signal-to-noise ratio of sqrt(m*p).
+        assert!((signal_to_noise(N, M, R, 1) - 32.74).abs() < 0.01);
+        assert!((signal_to_noise(N, M, R, 10_000) - 6.68).abs() < 0.01);
+        // With p^2*M close to 1 the capacity is about M / (2*rho^2).
+        assert_eq!(capacity(N, M, R, 5.0), 18_181);
+        assert_eq!(capacity(N, M, R, 2.0), 115_907);
+    }
+
+    #[test]
+    fn test_analysis_critical_distance() {
+        let c1 = critical_distance(N, M, R, 10_000);
+        let c2 = critical_distance(N, M, R, 50_000);
+        assert!(c1 > 0 && c1 < N/2);
+        assert!(c2 < c1);
+    }
+}
@@ -1,13 +1,13 @@
-///
-/// Import 'array_float_constants.rs'.
-/// The file was generated with values defined in 'build.rs'.
-///
+//
+// Import 'array_constants.rs'.
+// The file was generated with values defined in 'build.rs'.
+//
 #[allow(missing_docs)]
 include!(concat!(env!("OUT_DIR"), "/array_constants.rs"));
 
 extern crate rand;
-//
-use rand::distributions::{Distribution, Range};
+///
+use rand::distributions::{Distribution, Uniform};
 ///
 use array::rand::distributions::Bernoulli;
 ///
@@ -19,6 +19,8 @@ use std::collections::HashMap;
 ///
 use std::fmt;
 ///
+use hypervector::HyperVector;
+///
 /// Define an array of bool of dimension 'DIM1'.
 ///
 #[derive(Copy, Clone)]
@@ -43,13 +45,7 @@ impl Default for BoolArray {
 impl PartialEq for BoolArray {
 
 	fn eq(&self, other: &BoolArray) -> bool {
-		let mut ret: bool = false;
-		
-		for it in self.0.iter().zip(other.0.iter()) { 
-			let (ai, bi) = it;
-			if *bi == *ai { ret = true } else { ret = false }
-		}
-		ret
+		self.0.iter().zip(other.0.iter()).all(|(ai, bi)| ai == bi)
     }
 }
 ///
@@ -92,7 +88,7 @@ impl BoolArray {
 
     	let mut rng = thread_rng();
 
-        let inrange = Range::new(0, DIM);
+        let inrange = Uniform::new(0, DIM);
 
         while indices.len() < card {
             r = inrange.sample(&mut rng);
@@ -243,4 +239,48 @@ impl BitOr for BoolArray {
         BoolArray(a)
     }
 }
+///
+///
+///
+impl HyperVector for BoolArray {
+    fn random(bits: usize) -> Self {
+        assert_eq!(bits, DIM, "a BoolArray has {} bits", DIM);
+        BoolArray::new()
+    }
+    fn from_bools(bits: &[bool]) -> Self {
+        assert_eq!(bits.len(), DIM, "a BoolArray has {} bits", DIM);
+        BoolArray::new_from_vec(bits)
+    }
+    fn len(&self) -> usize { self.0.len() }
+    fn get(&self, i: usize) -> bool { BoolArray::get(self, i) }
+    fn set(&mut self, i: usize, value: bool) { BoolArray::set(self, i, value) }
+    fn distance(&self, other: &Self) -> usize {
+        self.0.iter().zip(other.0.iter()).filter(|&(a, b)| a != b).count()
+    }
+    fn xor(&self, other: &Self) -> Self { *self ^ *other }
+    fn and(&self, other: &Self) -> Self { *self & *other }
+    fn or(&self, other: &Self) -> Self { *self | *other }
+    fn popcount(&self) -> usize { self.count_ones() }
+}
+
+#[cfg(test)]
+mod tests {
 
+    use super::*;
+    use hypervector::tests::check;
+
+    #[test]
+    fn test_array_card() {
+        let a = BoolArray::new_card(10);
+        assert_eq!(a.count_ones(), 10);
+        assert_eq!(a.count_zeros(), DIM - 10);
+        let mut b = a;
+        b.set(0, !a.get(0));
+        assert!(a != b);
+    }
+
+    #[test]
+    fn test_array_hypervector() {
+        check::<BoolArray>(DIM);
+    }
+}
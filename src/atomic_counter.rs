@@ -0,0 +1,157 @@
+//
+// Lock-free counters for hard locations.
+//
+// Same layout as `Counter`, `DIM*bits()` counters per hard location, but every counter
+// is an `AtomicI16`: concurrent `input` calls update them through `&self` without locks.
+// An update is a compare-exchange loop that saturates at `i16::MIN` and `i16::MAX`
+// instead of wrapping around.
+//
+// Counters are independent: a concurrent reader sees every counter either before or
+// after an `input`, but may see some counters of a row updated and others not yet.
+//
+#[allow(missing_docs)]
+include!(concat!(env!("OUT_DIR"), "/dimensions.rs"));
+///
+use std::sync::atomic::{AtomicI16, Ordering};
+///
+use bittable::BitTable;
+///
+use counter::Counter;
+///
+/// Add `1`, or `-1` if `!up`, to `c`, saturating at the bounds of `i16`.
+///
+/// Returns `false` if the counter was already saturated.
+///
+fn bump(c: &AtomicI16, up: bool) -> bool {
+    let mut current = c.load(Ordering::Relaxed);
+    loop {
+        let next = if up { current.saturating_add(1) } else { current.saturating_sub(1) };
+        if next == current { return false; }
+        match c.compare_exchange_weak(current, next, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => return true,
+            Err(actual) => current = actual,
+        }
+    }
+}
+///
+/// Atomic counters of the hard locations.
+///
+#[derive(Debug)]
+pub struct AtomicCounter(Vec<AtomicI16>);
+///
+///
+///
+impl AtomicCounter {
+    ///
+    ///
+    ///
+    pub fn new() -> Self {
+        AtomicCounter((0..Counter::row() * ADD_DIM).map(|_| AtomicI16::new(0)).collect())
+    }
+    ///
+    /// Atomic copy of `c`.
+    ///
+    pub fn from_counter(c: &Counter) -> Self {
+        AtomicCounter(c.as_slice().iter().map(|&x| AtomicI16::new(x)).collect())
+    }
+    ///
+    /// Copy of the current values; concurrent `input` calls may be seen partially.
+    ///
+    pub fn to_counter(&self) -> Counter {
+        Counter::from_vec(self.0.iter().map(|x| x.load(Ordering::Relaxed)).collect())
+    }
+    ///
+    ///
+    ///
+    pub fn len(&self) -> usize { self.0.len() }
+    ///
+    /// Counter value for address `index`, at position `i`.
+    ///
+    pub fn get(&self, index: usize, i: usize) -> i16 {
+        self.0[index * Counter::row() + i].load(Ordering::Relaxed)
+    }
+    ///
+    /// Increment (`b`) or decrement the counter for address `index`, at position `i`.
+    ///
+    /// Returns `false` if the counter was saturated and kept its value.
+    ///
+    pub fn set(&self, index: usize, i: usize, b: bool) -> bool {
+        bump(&self.0[index * Counter::row() + i], b)
+    }
+    ///
+    /// Update the counters at address `index` using input `b`.
+    ///
+    pub fn input(&self, index: usize, b: &BitTable) {
+        let counters = &self.0[index * Counter::row()..(index + 1) * Counter::row()];
+        for (i, c) in counters.iter().enumerate() { bump(c, b.get(i)); }
+    }
+    ///
+    /// The counters at address `index`.
+    ///
+    pub fn get_counters(&self, index: usize) -> Vec<i16> {
+        self.0[index * Counter::row()..(index + 1) * Counter::row()].iter().map(|x| x.load(Ordering::Relaxed)).collect()
+    }
+    ///
+    /// Reset every counter to `0`.
+    ///
+    pub fn clear(&self) {
+        for x in self.0.iter() { x.store(0, Ordering::Relaxed); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::i16;
+    use std::sync::Arc;
+    use std::thread;
+    use super::*;
+
+    #[test]
+    fn test_atomic_counter_matches_counter() {
+        let mut c = Counter::new();
+        let a = AtomicCounter::new();
+        assert_eq!(a.len(), c.len());
+        for k in 0..5 {
+            let bt = BitTable::new_rand();
+            c.input(k * 7, &bt);
+            a.input(k * 7, &bt);
+        }
+        assert_eq!(a.to_counter().as_slice(), c.as_slice());
+        assert_eq!(a.get_counters(14), c.get_counters(14));
+        assert_eq!(AtomicCounter::from_counter(&c).to_counter().as_slice(), c.as_slice());
+        a.clear();
+        assert!(a.to_counter().as_slice().iter().all(|&x| x == 0));
+    }
+
+    #[test]
+    fn test_atomic_counter_saturates() {
+        let mut v = vec![0i16; Counter::row() * ADD_DIM];
+        v[0] = i16::MAX - 1;
+        v[1] = i16::MIN;
+        let a = AtomicCounter::from_counter(&Counter::from_vec(v));
+        assert!(a.set(0, 0, true));
+        assert!(!a.set(0, 0, true));
+        assert_eq!(a.get(0, 0), i16::MAX);
+        assert!(!a.set(0, 1, false));
+        assert_eq!(a.get(0, 1), i16::MIN);
+        assert!(a.set(0, 1, true));
+        assert_eq!(a.get(0, 1), i16::MIN + 1);
+    }
+
+    #[test]
+    fn test_atomic_counter_concurrent() {
+        // No increment is lost, and saturation holds under contention.
+        let a = Arc::new(AtomicCounter::new());
+        let handles: Vec<_> = (0..8).map(|_| {
+            let a = a.clone();
+            thread::spawn(move || {
+                for _ in 0..1000 { a.set(0, 0, true); }
+                for _ in 0..5000 { a.set(1, 0, true); }
+            })
+        }).collect();
+        for h in handles { h.join().unwrap(); }
+        assert_eq!(a.get(0, 0), 8000);
+        assert_eq!(a.get(1, 0), i16::MAX);
+    }
+}
@@ -0,0 +1,334 @@
+//
+// Command-line tool for creating, training and querying persisted memories.
+//
+//     kanerva init  <memory> [--seed S] [--radius R] [--embedding-dims D]
+//     kanerva write <memory> <patterns> [--radius R] [--format F]
+//     kanerva read  <memory> <cues> [--radius R] [--format F]
+//     kanerva stats <memory>
+//     kanerva bench <memory> [--patterns T] [--noise D] [--seed S] [--radius R]
+//     kanerva merge <output> <memory>...
+//
+// Pattern files are in one of the `patterns` formats, `bits`, `hex` (the default), `base64`
+// or `binary`. `read` writes the read data to the standard output, in the same format.
+//
+// The address length and the number of hard locations are those of the build, printed by
+// `init` and `stats`.
+//
+extern crate kanerva;
+extern crate bittable;
+///
+use std::collections::HashMap;
+///
+use std::env;
+///
+use std::fs::File;
+///
+use std::io::{self, BufReader};
+///
+use std::path::Path;
+///
+use std::process;
+///
+use std::time::{Duration, Instant};
+///
+use bittable::BitTable;
+///
+use kanerva::analysis;
+///
+use kanerva::encoder::simhash::SimHashEncoder;
+///
+use kanerva::merge;
+///
+use kanerva::noise::Noise;
+///
+use kanerva::patterns::{PatternFormat, PatternReader, PatternWriter};
+///
+use kanerva::store::{self, Memory};
+///
+use kanerva::vsa;
+///
+///
+///
+const USAGE: &str = "\
+usage: kanerva <command> [<args>]
+
+commands:
+    init  <memory> [--seed S] [--radius R] [--embedding-dims D]
+    write <memory> <patterns> [--radius R] [--format bits|hex|base64|binary]
+    read  <memory> <cues> [--radius R] [--format bits|hex|base64|binary]
+    stats <memory>
+    bench <memory> [--patterns T] [--noise D] [--seed S] [--radius R]
+    merge <output> <memory>...";
+///
+/// Positional arguments and `--key value` options of a command.
+///
+#[derive(Debug)]
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+///
+///
+///
+impl Args {
+    ///
+    /// Split `args`, accepting only the options in `allowed`.
+    ///
+    fn parse(args: &[String], allowed: &[&str]) -> Result<Self, String> {
+        let mut positional: Vec<String> = Vec::new();
+        let mut options: HashMap<String, String> = HashMap::new();
+        let mut it = args.iter();
+        while let Some(a) = it.next() {
+            if a.starts_with("--") {
+                let key = &a[2..];
+                if !allowed.contains(&key) { return Err(format!("unknown option `{}`", a)); }
+                let value = it.next().ok_or_else(|| format!("missing value for `{}`", a))?;
+                options.insert(key.to_string(), value.clone());
+            } else {
+                positional.push(a.clone());
+            }
+        }
+        Ok(Args { positional, options })
+    }
+    ///
+    /// Positional argument `i`, named `name` in errors.
+    ///
+    fn arg(&self, i: usize, name: &str) -> Result<&str, String> {
+        self.positional.get(i).map(|s| s.as_str()).ok_or_else(|| format!("missing <{}>", name))
+    }
+    ///
+    /// Numeric option `key`, or `default`.
+    ///
+    fn number(&self, key: &str, default: u64) -> Result<u64, String> {
+        match self.options.get(key) {
+            Some(v) => v.parse().map_err(|_| format!("`--{}` expects a number, found `{}`", key, v)),
+            None => Ok(default),
+        }
+    }
+}
+///
+///
+///
+fn load(path: &str) -> Result<Memory, String> {
+    Memory::load(path).map_err(|e| format!("{}: {}", path, e))
+}
+///
+///
+///
+fn save(m: &Memory, path: &str) -> Result<(), String> {
+    m.save(path).map_err(|e| format!("{}: {}", path, e))
+}
+///
+/// Format given by `--format`, hexadecimal by default.
+///
+fn format(a: &Args) -> Result<PatternFormat, String> {
+    a.options.get("format").map_or(Ok(PatternFormat::Hex), |f| f.parse())
+}
+///
+/// Open a pattern file for streaming.
+///
+fn open_patterns(path: &str, format: PatternFormat) -> Result<PatternReader<BufReader<File>>, String> {
+    let f = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    PatternReader::new(BufReader::new(f), format).map_err(|e| format!("{}: {}", path, e))
+}
+///
+///
+///
+fn micros(d: Duration) -> f64 {
+    d.as_secs() as f64 * 1e6 + f64::from(d.subsec_nanos()) / 1e3
+}
+///
+///
+///
+fn init(args: &[String]) -> Result<(), String> {
+    let a = Args::parse(args, &["seed", "radius", "embedding-dims"])?;
+    let path = a.arg(0, "memory")?;
+    let n = BitTable::new().len();
+    let m = store::hard_locations();
+    if Path::new(path).exists() {
+        return Err(format!("{}: already exists", path));
+    }
+    let seed = a.number("seed", 0)?;
+    let radius = a.number("radius", analysis::default_radius(n, m) as u64)? as usize;
+    let mut memory = Memory::new(seed, radius);
+    match a.number("embedding-dims", 0)? {
+        0 => {},
+        d => memory.set_projection(SimHashEncoder::new(d as usize, seed)),
+    }
+    save(&memory, path)?;
+    println!("{}: {} bits, {} hard locations, radius {}, seed {}", path, n, m, radius, seed);
+    Ok(())
+}
+///
+///
+///
+fn write(args: &[String]) -> Result<(), String> {
+    let a = Args::parse(args, &["radius", "format"])?;
+    let path = a.arg(0, "memory")?;
+    let patterns_path = a.arg(1, "patterns")?;
+    let patterns = open_patterns(patterns_path, format(&a)?)?;
+    let mut memory = load(path)?;
+    let radius = memory.radius();
+    memory.set_radius(a.number("radius", radius as u64)? as usize);
+    let count = memory.write_batch(patterns).map_err(|e| format!("{}: {}", patterns_path, e))?;
+    memory.set_radius(radius);
+    save(&memory, path)?;
+    println!("{}: wrote {} patterns", path, count);
+    Ok(())
+}
+///
+///
+///
+fn read(args: &[String]) -> Result<(), String> {
+    let a = Args::parse(args, &["radius", "format"])?;
+    let mut memory = load(a.arg(0, "memory")?)?;
+    let cues_path = a.arg(1, "cues")?;
+    let cues = open_patterns(cues_path, format(&a)?)?;
+    let radius = memory.radius();
+    memory.set_radius(a.number("radius", radius as u64)? as usize);
+    let stdout = io::stdout();
+    let mut out = PatternWriter::new(stdout.lock(), format(&a)?).map_err(|e| e.to_string())?;
+    for cue in cues {
+        let (cue, _) = cue.map_err(|e| format!("{}: {}", cues_path, e))?;
+        let data = memory.read(&cue);
+        out.write(&data, &data).map_err(|e| e.to_string())?;
+    }
+    out.flush().map_err(|e| e.to_string())
+}
+///
+///
+///
+fn stats(args: &[String]) -> Result<(), String> {
+    let a = Args::parse(args, &[])?;
+    let path = a.arg(0, "memory")?;
+    let memory = load(path)?;
+    let n = BitTable::new().len();
+    let m = memory.sdm().address_space().len();
+    let r = memory.radius();
+    let counters = memory.sdm().counter().as_slice();
+    let used = counters.chunks(n).filter(|c| c.iter().any(|&x| x != 0)).count();
+    let max = counters.iter().map(|&x| i32::from(x).abs()).max().unwrap_or(0);
+    let t = memory.writes() as usize;
+    println!("memory:              {}", path);
+    println!("bits:                {}", n);
+    println!("hard locations:      {}", m);
+    println!("seed:                {}", memory.seed());
+    println!("radius:              {}", r);
+    println!("activation:          {:.5} ({:.1} locations)", analysis::activation_probability(n, r), analysis::expected_activated(n, m, r));
+    println!("writes:              {}", t);
+    println!("used locations:      {}", used);
+    println!("max |counter|:       {}", max);
+    println!("capacity (snr >= 3): {}", analysis::capacity(n, m, r, 3.0));
+    let s = memory.stats();
+    println!("remaining capacity:  {}{}", s.remaining_capacity.unwrap_or(0), if s.is_overloaded() { " (overloaded)" } else { "" });
+    println!("saturated counters:  {:.5}", s.saturated);
+    if let (Some(low), Some(high)) = (s.counters.first(), s.counters.last()) {
+        println!("counter range:       {}..{}", low.0, high.0);
+    }
+    if t > 0 {
+        println!("bit error (exact):   {:.5}", analysis::bit_error_probability(n, m, r, t));
+        println!("critical distance:   {}", analysis::critical_distance(n, m, r, t));
+    }
+    match memory.projection() {
+        Some(p) => println!("projection:          {} dims, seed {}, version {}", p.dims(), p.seed(), p.version()),
+        None => println!("projection:          none"),
+    }
+    Ok(())
+}
+///
+/// Write random patterns into a copy of the memory and read them back from noisy cues.
+///
+fn bench(args: &[String]) -> Result<(), String> {
+    let a = Args::parse(args, &["patterns", "noise", "seed", "radius"])?;
+    let mut memory = load(a.arg(0, "memory")?)?;
+    let t = a.number("patterns", 100)?;
+    let noise = a.number("noise", 8)? as usize;
+    let seed = a.number("seed", 0)?;
+    let radius = memory.radius();
+    memory.set_radius(a.number("radius", radius as u64)? as usize);
+    let patterns: Vec<BitTable> = (0..t).map(|k| vsa::random(seed.wrapping_add(k))).collect();
+    let start = Instant::now();
+    for p in patterns.iter() { memory.write(p, p); }
+    let write_time = start.elapsed();
+    let start = Instant::now();
+    let mut exact = 0;
+    let mut total = 0;
+    for (k, p) in patterns.iter().enumerate() {
+        let cue = p.cue_at_distance(noise, seed.wrapping_add(t).wrapping_add(k as u64));
+        let d = memory.read(&cue).distance(p);
+        if d == 0 { exact += 1; }
+        total += d;
+    }
+    let read_time = start.elapsed();
+    let t = patterns.len().max(1) as f64;
+    println!("patterns:      {}", patterns.len());
+    println!("cue noise:     {} bits", noise);
+    println!("radius:        {}", memory.radius());
+    println!("exact recall:  {:.1}%", 100.0 * f64::from(exact) / t);
+    println!("mean distance: {:.2}", total as f64 / t);
+    println!("write:         {:.1} us/pattern", micros(write_time) / t);
+    println!("read:          {:.1} us/pattern", micros(read_time) / t);
+    Ok(())
+}
+///
+/// Merge memories trained separately into a new memory file.
+///
+fn merge(args: &[String]) -> Result<(), String> {
+    let a = Args::parse(args, &[])?;
+    let path = a.arg(0, "output")?;
+    a.arg(1, "memory")?;
+    if Path::new(path).exists() {
+        return Err(format!("{}: already exists", path));
+    }
+    let (memory, saturated) = merge::merge_files(&a.positional[1..]).map_err(|e| e.to_string())?;
+    save(&memory, path)?;
+    println!("{}: merged {} memories, {} writes, {} saturated counters", path, a.positional.len() - 1, memory.writes(), saturated);
+    Ok(())
+}
+///
+///
+///
+fn run(args: &[String]) -> Result<(), String> {
+    let (command, rest) = match args.split_first() {
+        Some((c, rest)) => (c.as_str(), rest),
+        None => return Err(USAGE.to_string()),
+    };
+    match command {
+        "init" => init(rest),
+        "write" => write(rest),
+        "read" => read(rest),
+        "stats" => stats(rest),
+        "bench" => bench(rest),
+        "merge" => merge(rest),
+        "help" | "--help" | "-h" => { println!("{}", USAGE); Ok(()) },
+        _ => Err(format!("unknown command `{}`\n{}", command, USAGE)),
+    }
+}
+///
+///
+///
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("kanerva: {}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Args;
+
+    #[test]
+    fn test_cli_args() {
+        let args: Vec<String> = ["m.sdm", "--radius", "50", "p.txt"].iter().map(|s| s.to_string()).collect();
+        let a = Args::parse(&args, &["radius"]).unwrap();
+        assert_eq!(a.arg(1, "patterns").unwrap(), "p.txt");
+        assert_eq!(a.number("radius", 0).unwrap(), 50);
+        assert_eq!(a.number("seed", 7).unwrap(), 7);
+        assert!(a.arg(2, "x").is_err());
+        assert!(Args::parse(&args, &[]).is_err());
+        assert!(Args::parse(&args[..2], &["radius"]).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+//
+// HTTP/JSON server hosting named memories, built with `--features server`.
+//
+//     kanerva-server [--listen ADDR] [--data DIR] [--threads N]
+//
+// Memories saved in the data directory as `<name>.sdm` are loaded at startup, and the
+// `snapshot` endpoint saves them there. See `kanerva::server` for the endpoints.
+//
+extern crate kanerva;
+///
+use std::env;
+///
+use std::path::PathBuf;
+///
+use std::process;
+///
+use kanerva::server::{Registry, Server};
+///
+///
+///
+const USAGE: &str = "usage: kanerva-server [--listen ADDR] [--data DIR] [--threads N]";
+///
+///
+///
+fn run(args: &[String]) -> Result<(), String> {
+    let mut listen = "127.0.0.1:8080".to_string();
+    let mut dir: Option<PathBuf> = None;
+    let mut threads: usize = 4;
+    let mut it = args.iter();
+    while let Some(a) = it.next() {
+        let mut value = || it.next().cloned().ok_or_else(|| format!("missing value for `{}`", a));
+        match a.as_str() {
+            "--listen" => listen = value()?,
+            "--data" => dir = Some(PathBuf::from(value()?)),
+            "--threads" => {
+                let v = value()?;
+                threads = v.parse().map_err(|_| format!("`--threads` expects a number, found `{}`", v))?;
+            },
+            "--help" | "-h" => { println!("{}", USAGE); return Ok(()); },
+            _ => return Err(format!("unknown argument `{}`\n{}", a, USAGE)),
+        }
+    }
+    let registry = Registry::new(dir).map_err(|e| e.to_string())?;
+    let server = Server::bind(&listen, registry).map_err(|e| format!("{}: {}", listen, e))?;
+    println!("kanerva-server: listening on http://{}", server.local_addr());
+    server.run(threads);
+    Ok(())
+}
+///
+///
+///
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("kanerva-server: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,71 @@
+//
+// Worker process holding one shard of a distributed memory.
+//
+//     kanerva-worker --seed N --shard K/SHARDS [--listen ADDR]
+//
+// The worker holds hard locations `kanerva::distributed::shard_range(K, SHARDS)` of the
+// address space drawn from the seed, and answers the requests of `ShardedSdm`.
+//
+extern crate kanerva;
+///
+use std::env;
+///
+use std::net::TcpListener;
+///
+use std::process;
+///
+use kanerva::distributed::{serve, shard_range, Shard};
+///
+///
+///
+const USAGE: &str = "usage: kanerva-worker --seed N --shard K/SHARDS [--listen ADDR]";
+///
+/// Parse `K/SHARDS`.
+///
+fn parse_shard(v: &str) -> Result<(usize, usize), String> {
+    let err = || format!("`--shard` expects K/SHARDS with K < SHARDS, found `{}`", v);
+    let mut parts = v.splitn(2, '/');
+    let k: usize = parts.next().and_then(|x| x.parse().ok()).ok_or_else(err)?;
+    let n: usize = parts.next().and_then(|x| x.parse().ok()).ok_or_else(err)?;
+    if k >= n { return Err(err()); }
+    Ok((k, n))
+}
+///
+///
+///
+fn run(args: &[String]) -> Result<(), String> {
+    let mut listen = "127.0.0.1:7000".to_string();
+    let mut seed: Option<u64> = None;
+    let mut shard: Option<(usize, usize)> = None;
+    let mut it = args.iter();
+    while let Some(a) = it.next() {
+        let mut value = || it.next().cloned().ok_or_else(|| format!("missing value for `{}`", a));
+        match a.as_str() {
+            "--listen" => listen = value()?,
+            "--seed" => {
+                let v = value()?;
+                seed = Some(v.parse().map_err(|_| format!("`--seed` expects a number, found `{}`", v))?);
+            },
+            "--shard" => shard = Some(parse_shard(&value()?)?),
+            "--help" | "-h" => { println!("{}", USAGE); return Ok(()); },
+            _ => return Err(format!("unknown argument `{}`\n{}", a, USAGE)),
+        }
+    }
+    let seed = seed.ok_or_else(|| format!("missing `--seed`\n{}", USAGE))?;
+    let (k, n) = shard.ok_or_else(|| format!("missing `--shard`\n{}", USAGE))?;
+    let locations = shard_range(k, n);
+    let listener = TcpListener::bind(&listen).map_err(|e| format!("{}: {}", listen, e))?;
+    let addr = listener.local_addr().map_err(|e| e.to_string())?;
+    println!("kanerva-worker: shard {}/{} (locations {}..{}) listening on {}", k, n, locations.start, locations.end, addr);
+    serve(listener, Shard::new(seed, locations)).map_err(|e| e.to_string())
+}
+///
+///
+///
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("kanerva-worker: {}", e);
+        process::exit(1);
+    }
+}
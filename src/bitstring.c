@@ -3,6 +3,7 @@
 #include <stdio.h>
 #include <string.h>
 #include <assert.h>
+#include <inttypes.h>
 #include "lib/base64.h"
 #include "bitstring.h"
 
@@ -89,7 +90,7 @@ void bs_init_random(bitstring_t *bs, unsigned int len, unsigned int bits_remaini
 void bs_init_hex(bitstring_t *bs, unsigned int len, char *hex) {
 	unsigned int i;
 	for (i=0; i<len; i++) {
-		sscanf(hex, "%016llx", bs);
+		sscanf(hex, "%016" SCNx64, bs);
 		bs++;
 		hex += 16;
 	}
@@ -114,7 +115,7 @@ We could have used the following code, but it would change the output if the pro
 void bs_to_hex(char *buf, bitstring_t *bs, unsigned int len) {
 	unsigned int i;
 	for (i=0; i<len; i++) {
-		sprintf(buf, "%016llx", *bs);
+		sprintf(buf, "%016" PRIx64, *bs);
 		bs++;
 		buf += 16;
 	}
@@ -162,10 +163,10 @@ void bs_average(bitstring_t *bs1, const bitstring_t *bs2, const unsigned int len
 		c = bs1[i] ^ bs2[i];
 		bit = 0;
 		while (c) {
-			if (c && 1) {
+			if (c & 1) {
 				// Flip a coin! :)
 				if (arc4random() % 2) {
-					bs1[i] ^= (1<<bit);
+					bs1[i] ^= ((bitstring_t)1<<bit);
 				}
 			}
 			bit++;
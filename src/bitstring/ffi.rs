@@ -0,0 +1,162 @@
+//
+// Declarations of the C functions of `src/bitstring.h` and `src/lib/base64.h`.
+//
+// Written by hand, in the layout rust-bindgen would produce: keep them in step with
+// the C headers when the library changes. `bs_init_bitcount_table` is left out on
+// purpose: it prints its whole table to stdout.
+//
+
+pub type bitstring_t = u64;
+extern "C" {
+    pub fn Base64encode_len(len: ::std::os::raw::c_int) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn Base64encode(
+        coded_dst: *mut ::std::os::raw::c_char,
+        plain_src: *const ::std::os::raw::c_char,
+        len_plain_src: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn Base64decode_len(coded_src: *const ::std::os::raw::c_char) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn Base64decode(
+        plain_dst: *mut ::std::os::raw::c_char,
+        coded_src: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn bs_alloc(len: ::std::os::raw::c_uint) -> *mut bitstring_t;
+}
+extern "C" {
+    pub fn bs_free(bs: *mut bitstring_t);
+}
+extern "C" {
+    pub fn bs_calculate_params(
+        bits: ::std::os::raw::c_uint,
+        len: *mut ::std::os::raw::c_uint,
+        bits_remaining: *mut ::std::os::raw::c_uint,
+    );
+}
+extern "C" {
+    pub fn bs_clear_remaining_bits(
+        bs: *mut bitstring_t,
+        len: ::std::os::raw::c_uint,
+        bits_remaining: ::std::os::raw::c_uint,
+    );
+}
+extern "C" {
+    pub fn bs_init_zeros(
+        bs: *mut bitstring_t,
+        len: ::std::os::raw::c_uint,
+        bits_remaining: ::std::os::raw::c_uint,
+    );
+}
+extern "C" {
+    pub fn bs_init_ones(
+        bs: *mut bitstring_t,
+        len: ::std::os::raw::c_uint,
+        bits_remaining: ::std::os::raw::c_uint,
+    );
+}
+extern "C" {
+    pub fn bs_init_random(
+        bs: *mut bitstring_t,
+        len: ::std::os::raw::c_uint,
+        bits_remaining: ::std::os::raw::c_uint,
+    );
+}
+extern "C" {
+    pub fn bs_init_hex(
+        bs: *mut bitstring_t,
+        len: ::std::os::raw::c_uint,
+        hex: *mut ::std::os::raw::c_char,
+    );
+}
+extern "C" {
+    pub fn bs_init_b64(bs: *mut bitstring_t, b64: *mut ::std::os::raw::c_char);
+}
+extern "C" {
+    pub fn bs_copy(dst: *mut bitstring_t, src: *const bitstring_t, len: ::std::os::raw::c_uint);
+}
+extern "C" {
+    pub fn bs_to_hex(
+        buf: *mut ::std::os::raw::c_char,
+        bs: *mut bitstring_t,
+        len: ::std::os::raw::c_uint,
+    );
+}
+extern "C" {
+    pub fn bs_to_b64(
+        buf: *mut ::std::os::raw::c_char,
+        bs: *mut bitstring_t,
+        len: ::std::os::raw::c_uint,
+    );
+}
+extern "C" {
+    pub fn bs_distance_popcount(
+        bs1: *const bitstring_t,
+        bs2: *const bitstring_t,
+        len: ::std::os::raw::c_uint,
+    ) -> ::std::os::raw::c_uint;
+}
+extern "C" {
+    pub fn bs_distance_lookup16(
+        bs1: *const bitstring_t,
+        bs2: *const bitstring_t,
+        len: ::std::os::raw::c_uint,
+    ) -> ::std::os::raw::c_uint;
+}
+extern "C" {
+    pub fn bs_distance_naive(
+        bs1: *const bitstring_t,
+        bs2: *const bitstring_t,
+        len: ::std::os::raw::c_uint,
+    ) -> ::std::os::raw::c_uint;
+}
+extern "C" {
+    pub fn bs_distance(
+        bs1: *const bitstring_t,
+        bs2: *const bitstring_t,
+        len: ::std::os::raw::c_uint,
+    ) -> ::std::os::raw::c_uint;
+}
+extern "C" {
+    pub fn bs_get_bit(
+        this: *mut bitstring_t,
+        bit: ::std::os::raw::c_uint,
+    ) -> ::std::os::raw::c_uint;
+}
+extern "C" {
+    pub fn bs_set_bit(
+        bs: *mut bitstring_t,
+        bit: ::std::os::raw::c_uint,
+        value: ::std::os::raw::c_uint,
+    );
+}
+extern "C" {
+    pub fn bs_flip_bit(bs: *mut bitstring_t, bit: ::std::os::raw::c_uint);
+}
+extern "C" {
+    pub fn bs_flip_random_bits(
+        bs: *mut bitstring_t,
+        bits: ::std::os::raw::c_uint,
+        flips: ::std::os::raw::c_uint,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn bs_not(bs: *mut bitstring_t, len: ::std::os::raw::c_uint);
+}
+extern "C" {
+    pub fn bs_xor(bs1: *mut bitstring_t, bs2: *const bitstring_t, len: ::std::os::raw::c_uint);
+}
+extern "C" {
+    pub fn bs_and(bs1: *mut bitstring_t, bs2: *const bitstring_t, len: ::std::os::raw::c_uint);
+}
+extern "C" {
+    pub fn bs_or(bs1: *mut bitstring_t, bs2: *const bitstring_t, len: ::std::os::raw::c_uint);
+}
+extern "C" {
+    pub fn bs_average(bs1: *mut bitstring_t, bs2: *const bitstring_t, len: ::std::os::raw::c_uint);
+}
@@ -0,0 +1,397 @@
+//!
+//! [`Bitstring`] is a safe wrapper over the C `bitstring_t` library (`src/bitstring.c`).
+//!
+//! A `Bitstring` owns a buffer allocated by `bs_alloc` and released by `bs_free` on drop.
+//! The buffer holds `len` words of 64 bits; the `bits_remaining` unused bits
+//! at the end of the last word are kept cleared.
+//!
+//! [`Bitstring`]: struct.Bitstring.html
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use kanerva::bitstring::Bitstring;
+//!
+//! let a = Bitstring::random(1000);
+//! let b = a.clone();
+//! assert_eq!(a.distance(&b), 0);
+//! ```
+///
+/// Raw declarations of the `bs_*` and `Base64*` API, written by hand after `bitstring.h`.
+///
+#[allow(dead_code)]
+mod ffi;
+///
+use std::fmt;
+///
+use std::ffi::CString;
+///
+use std::os::raw::{c_char, c_uint};
+///
+use self::ffi::bitstring_t;
+///
+use hypervector::HyperVector;
+///
+/// Number of bits in a `bitstring_t` word.
+///
+const WORD_BITS: usize = 64;
+///
+/// An owned C bit string of a given number of bits.
+///
+pub struct Bitstring {
+    ptr: *mut bitstring_t,
+    bits: usize,
+    len: c_uint,
+    bits_remaining: c_uint,
+}
+///
+/// The buffer is owned exclusively and never aliased.
+///
+unsafe impl Send for Bitstring {}
+///
+///
+///
+impl Bitstring {
+    ///
+    /// Allocate an uninitialized bit string of `bits` bits.
+    ///
+    fn alloc(bits: usize) -> Self {
+        assert!(bits > 0, "a bit string needs at least one bit");
+        let mut len: c_uint = 0;
+        let mut bits_remaining: c_uint = 0;
+        let ptr = unsafe {
+            ffi::bs_calculate_params(bits as c_uint, &mut len, &mut bits_remaining);
+            ffi::bs_alloc(len)
+        };
+        assert!(!ptr.is_null(), "bs_alloc failed for {} bits", bits);
+        Bitstring { ptr, bits, len, bits_remaining }
+    }
+    ///
+    /// A bit string of `bits` bits, all set to `0`.
+    ///
+    pub fn zeros(bits: usize) -> Self {
+        let bs = Bitstring::alloc(bits);
+        unsafe { ffi::bs_init_zeros(bs.ptr, bs.len, bs.bits_remaining) };
+        bs
+    }
+    ///
+    /// A bit string of `bits` bits, all set to `1`.
+    ///
+    pub fn ones(bits: usize) -> Self {
+        let bs = Bitstring::alloc(bits);
+        unsafe { ffi::bs_init_ones(bs.ptr, bs.len, bs.bits_remaining) };
+        bs
+    }
+    ///
+    /// A uniformly random bit string of `bits` bits.
+    ///
+    pub fn random(bits: usize) -> Self {
+        let bs = Bitstring::alloc(bits);
+        unsafe { ffi::bs_init_random(bs.ptr, bs.len, bs.bits_remaining) };
+        bs
+    }
+    ///
+    /// Parse the output of `to_hex`: 16 hex digits per word.
+    ///
+    /// Returns `None` if the length or the digits are invalid.
+    ///
+    pub fn from_hex(bits: usize, hex: &str) -> Option<Self> {
+        let bs = Bitstring::alloc(bits);
+        if hex.len() != bs.words() * WORD_BITS / 4 || !hex.bytes().all(|c| (c as char).is_digit(16)) {
+            return None;
+        }
+        let chex = CString::new(hex).ok()?;
+        unsafe {
+            ffi::bs_init_hex(bs.ptr, bs.len, chex.as_ptr() as *mut c_char);
+            ffi::bs_clear_remaining_bits(bs.ptr, bs.len, bs.bits_remaining);
+        }
+        Some(bs)
+    }
+    ///
+    /// Parse the output of `to_b64`.
+    ///
+    /// Returns `None` if the string does not decode to exactly the buffer size.
+    ///
+    pub fn from_b64(bits: usize, b64: &str) -> Option<Self> {
+        let bs = Bitstring::alloc(bits);
+        let cb64 = CString::new(b64).ok()?;
+        // `bs_init_b64` decodes in place and writes a trailing `\0` past the buffer,
+        // so decode into a scratch buffer first.
+        let cap = unsafe { ffi::Base64decode_len(cb64.as_ptr()) } as usize;
+        let mut plain: Vec<u8> = vec![0; cap + 1];
+        let n = unsafe { ffi::Base64decode(plain.as_mut_ptr() as *mut c_char, cb64.as_ptr()) } as usize;
+        let size = bs.words() * WORD_BITS / 8;
+        if n != size || b64.len() != (size + 2) / 3 * 4 {
+            return None;
+        }
+        unsafe {
+            ::std::ptr::copy_nonoverlapping(plain.as_ptr(), bs.ptr as *mut u8, size);
+            ffi::bs_clear_remaining_bits(bs.ptr, bs.len, bs.bits_remaining);
+        }
+        Some(bs)
+    }
+    ///
+    /// Number of bits.
+    ///
+    pub fn len(&self) -> usize { self.bits }
+    ///
+    /// Number of 64-bit words in the buffer.
+    ///
+    pub fn words(&self) -> usize { self.len as usize }
+    ///
+    /// The buffer as a slice of words.
+    ///
+    pub fn as_slice(&self) -> &[u64] {
+        unsafe { ::std::slice::from_raw_parts(self.ptr, self.words()) }
+    }
+    ///
+    /// Get bit `i`.
+    ///
+    pub fn get(&self, i: usize) -> bool {
+        assert!(i < self.bits, "index {} out of bounds", i);
+        unsafe { ffi::bs_get_bit(self.ptr, i as c_uint) != 0 }
+    }
+    ///
+    /// Set bit `i` to `value`.
+    ///
+    pub fn set(&mut self, i: usize, value: bool) {
+        assert!(i < self.bits, "index {} out of bounds", i);
+        unsafe { ffi::bs_set_bit(self.ptr, i as c_uint, value as c_uint) }
+    }
+    ///
+    /// Flip bit `i`.
+    ///
+    pub fn flip(&mut self, i: usize) {
+        assert!(i < self.bits, "index {} out of bounds", i);
+        unsafe { ffi::bs_flip_bit(self.ptr, i as c_uint) }
+    }
+    ///
+    /// Flip `flips` distinct random bits; returns the number of flipped bits.
+    ///
+    pub fn flip_random_bits(&mut self, flips: usize) -> usize {
+        assert!(flips <= self.bits, "cannot flip {} bits out of {}", flips, self.bits);
+        unsafe { ffi::bs_flip_random_bits(self.ptr, self.bits as c_uint, flips as c_uint) as usize }
+    }
+    ///
+    /// Number of bits set to `1`.
+    ///
+    pub fn ones_count(&self) -> usize {
+        self.as_slice().iter().map(|w| w.count_ones() as usize).sum()
+    }
+    ///
+    /// Hamming distance.
+    ///
+    pub fn distance(&self, other: &Bitstring) -> usize {
+        self.check_len(other);
+        unsafe { ffi::bs_distance(self.ptr, other.ptr, self.len) as usize }
+    }
+    ///
+    /// `self ^= other`.
+    ///
+    pub fn xor(&mut self, other: &Bitstring) {
+        self.check_len(other);
+        unsafe { ffi::bs_xor(self.ptr, other.ptr, self.len) }
+    }
+    ///
+    /// `self &= other`.
+    ///
+    pub fn and(&mut self, other: &Bitstring) {
+        self.check_len(other);
+        unsafe { ffi::bs_and(self.ptr, other.ptr, self.len) }
+    }
+    ///
+    /// `self |= other`.
+    ///
+    pub fn or(&mut self, other: &Bitstring) {
+        self.check_len(other);
+        unsafe { ffi::bs_or(self.ptr, other.ptr, self.len) }
+    }
+    ///
+    /// `self = !self`, with the remaining bits kept cleared.
+    ///
+    pub fn not(&mut self) {
+        unsafe {
+            ffi::bs_not(self.ptr, self.len);
+            ffi::bs_clear_remaining_bits(self.ptr, self.len, self.bits_remaining);
+        }
+    }
+    ///
+    /// Average with `other`: keep the common bits, pick the differing ones at random.
+    ///
+    pub fn average(&mut self, other: &Bitstring) {
+        self.check_len(other);
+        unsafe { ffi::bs_average(self.ptr, other.ptr, self.len) }
+    }
+    ///
+    /// Hexadecimal representation, 16 digits per word.
+    ///
+    pub fn to_hex(&self) -> String {
+        let mut buf: Vec<u8> = vec![0; self.words() * WORD_BITS / 4 + 1];
+        unsafe { ffi::bs_to_hex(buf.as_mut_ptr() as *mut c_char, self.ptr, self.len) };
+        buf.pop();
+        String::from_utf8(buf).expect("bs_to_hex produced invalid UTF-8")
+    }
+    ///
+    /// Base64 representation of the buffer bytes.
+    ///
+    pub fn to_b64(&self) -> String {
+        let size = unsafe { ffi::Base64encode_len((self.words() * WORD_BITS / 8) as i32) } as usize;
+        let mut buf: Vec<u8> = vec![0; size];
+        unsafe { ffi::bs_to_b64(buf.as_mut_ptr() as *mut c_char, self.ptr, self.len) };
+        buf.pop();
+        String::from_utf8(buf).expect("bs_to_b64 produced invalid UTF-8")
+    }
+    ///
+    ///
+    ///
+    fn check_len(&self, other: &Bitstring) {
+        assert_eq!(self.bits, other.bits, "bit strings of different lengths");
+    }
+}
+///
+///
+///
+impl Drop for Bitstring {
+    fn drop(&mut self) {
+        unsafe { ffi::bs_free(self.ptr) }
+    }
+}
+///
+///
+///
+impl Clone for Bitstring {
+    fn clone(&self) -> Self {
+        let bs = Bitstring::alloc(self.bits);
+        unsafe { ffi::bs_copy(bs.ptr, self.ptr, self.len) };
+        bs
+    }
+}
+///
+///
+///
+impl PartialEq for Bitstring {
+    fn eq(&self, other: &Bitstring) -> bool {
+        self.bits == other.bits && self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for Bitstring {}
+///
+///
+///
+impl fmt::Debug for Bitstring {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BS.{{len() = {}, hex = {}}}", self.bits, self.to_hex())
+    }
+}
+///
+///
+///
+impl HyperVector for Bitstring {
+    fn random(bits: usize) -> Self { Bitstring::random(bits) }
+    fn from_bools(bits: &[bool]) -> Self {
+        let mut bs = Bitstring::zeros(bits.len());
+        for (i, _) in bits.iter().enumerate().filter(|&(_, &b)| b) { bs.set(i, true); }
+        bs
+    }
+    fn len(&self) -> usize { self.bits }
+    fn get(&self, i: usize) -> bool { Bitstring::get(self, i) }
+    fn set(&mut self, i: usize, value: bool) { Bitstring::set(self, i, value) }
+    fn distance(&self, other: &Self) -> usize { Bitstring::distance(self, other) }
+    fn xor(&self, other: &Self) -> Self {
+        let mut bs = self.clone();
+        Bitstring::xor(&mut bs, other);
+        bs
+    }
+    fn and(&self, other: &Self) -> Self {
+        let mut bs = self.clone();
+        Bitstring::and(&mut bs, other);
+        bs
+    }
+    fn or(&self, other: &Self) -> Self {
+        let mut bs = self.clone();
+        Bitstring::or(&mut bs, other);
+        bs
+    }
+    fn popcount(&self) -> usize { self.ones_count() }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Bitstring;
+    use super::WORD_BITS;
+    use hypervector::tests::check;
+
+    #[test]
+    fn test_bitstring_params() {
+        let bs = Bitstring::zeros(1000);
+        assert_eq!(bs.len(), 1000);
+        assert_eq!(bs.words(), (1000 + WORD_BITS - 1) / WORD_BITS);
+        assert_eq!(bs.ones_count(), 0);
+        assert_eq!(Bitstring::ones(1000).ones_count(), 1000);
+    }
+
+    #[test]
+    fn test_bitstring_get_set_flip() {
+        let mut bs = Bitstring::zeros(130);
+        bs.set(129, true);
+        assert!(bs.get(129));
+        bs.flip(129);
+        assert!(!bs.get(129));
+        assert_eq!(bs.flip_random_bits(17), 17);
+        assert_eq!(bs.ones_count(), 17);
+    }
+
+    #[test]
+    fn test_bitstring_distance() {
+        let a = Bitstring::random(1000);
+        let mut b = a.clone();
+        assert_eq!(a.distance(&b), 0);
+        b.flip(3);
+        b.flip(999);
+        assert_eq!(a.distance(&b), 2);
+        b.xor(&a);
+        assert_eq!(b.ones_count(), 2);
+    }
+
+    #[test]
+    fn test_bitstring_not() {
+        let mut bs = Bitstring::zeros(100);
+        bs.not();
+        assert_eq!(bs, Bitstring::ones(100));
+    }
+
+    #[test]
+    fn test_bitstring_average() {
+        let mut a = Bitstring::zeros(256);
+        let b = Bitstring::ones(256);
+        a.average(&b);
+        let d = a.distance(&b);
+        assert!(d > 64 && d < 192, "average distance {}", d);
+    }
+
+    #[test]
+    fn test_bitstring_hex_round_trip() {
+        let a = Bitstring::random(1000);
+        let hex = a.to_hex();
+        assert_eq!(hex.len(), a.words() * 16);
+        assert_eq!(Bitstring::from_hex(1000, &hex), Some(a));
+        assert_eq!(Bitstring::from_hex(1000, &hex[1..]), None);
+        assert_eq!(Bitstring::from_hex(1000, &hex.replace("0", "g")).is_none(), hex.contains('0'));
+    }
+
+    #[test]
+    fn test_bitstring_hypervector() {
+        check::<Bitstring>(1000);
+        check::<Bitstring>(128);
+    }
+
+    #[test]
+    fn test_bitstring_b64_round_trip() {
+        let a = Bitstring::random(1000);
+        let b64 = a.to_b64();
+        assert_eq!(Bitstring::from_b64(1000, &b64), Some(a));
+        assert_eq!(Bitstring::from_b64(2000, &b64), None);
+    }
+}
@@ -0,0 +1,239 @@
+//
+// Stable C ABI of the staticlib build, declared in `include/kanerva.h`, which `build.rs`
+// generates from the `///` comments and signatures of this file.
+//
+// A `kanerva_sdm` is an opaque handle on a `store::Memory`, created by `kanerva_sdm_new`
+// or `kanerva_sdm_load` and released by `kanerva_sdm_free`. Addresses and data are
+// passed as `kanerva_address_bytes()` bytes: bit `i` is bit `7 - i % 8` of byte `i / 8`.
+//
+// Every function returning `int` returns `KANERVA_OK` or one of the error codes below;
+// panics are caught at the boundary and reported as `KANERVA_ERR_PANIC`.
+//
+///
+use std::ffi::CStr;
+///
+use std::io::ErrorKind;
+///
+use std::panic::{self, AssertUnwindSafe};
+///
+use std::ptr;
+///
+use std::slice;
+///
+use libc::{c_char, c_int, size_t};
+///
+use bittable::BitTable;
+///
+use analysis;
+///
+use encoding::{address_bytes, from_bytes, to_bytes};
+///
+use store::{hard_locations, Memory};
+///
+/// Success.
+///
+pub const KANERVA_OK: c_int = 0;
+///
+/// A required pointer is `NULL`.
+///
+pub const KANERVA_ERR_NULL: c_int = 1;
+///
+/// A buffer length is not `kanerva_address_bytes()`.
+///
+pub const KANERVA_ERR_LENGTH: c_int = 2;
+///
+/// The file could not be opened, read or written.
+///
+pub const KANERVA_ERR_IO: c_int = 3;
+///
+/// The file is not a memory of this build.
+///
+pub const KANERVA_ERR_FORMAT: c_int = 4;
+///
+/// The path is not valid UTF-8.
+///
+pub const KANERVA_ERR_PATH: c_int = 5;
+///
+/// Internal error.
+///
+pub const KANERVA_ERR_PANIC: c_int = 6;
+///
+/// Opaque handle of the C API.
+///
+#[derive(Debug)]
+pub struct KanervaSdm(Memory);
+///
+/// Run `f`, turning a panic into `KANERVA_ERR_PANIC`.
+///
+fn guard<F: FnOnce() -> c_int>(f: F) -> c_int {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(KANERVA_ERR_PANIC)
+}
+///
+///
+///
+unsafe fn path<'a>(p: *const c_char) -> Result<&'a str, c_int> {
+    if p.is_null() { return Err(KANERVA_ERR_NULL); }
+    CStr::from_ptr(p).to_str().map_err(|_| KANERVA_ERR_PATH)
+}
+///
+/// Number of bits of an address.
+///
+#[no_mangle]
+pub extern "C" fn kanerva_address_bits() -> size_t { BitTable::new().len() }
+///
+/// Number of bytes of an address buffer.
+///
+#[no_mangle]
+pub extern "C" fn kanerva_address_bytes() -> size_t { address_bytes() }
+///
+/// Number of hard locations.
+///
+#[no_mangle]
+pub extern "C" fn kanerva_hard_locations() -> size_t { hard_locations() }
+///
+/// A new empty memory drawn from `seed`; `radius` `0` picks the default radius.
+///
+/// Returns `NULL` on failure.
+///
+#[no_mangle]
+pub extern "C" fn kanerva_sdm_new(seed: u64, radius: size_t) -> *mut KanervaSdm {
+    panic::catch_unwind(|| {
+        let r = if radius == 0 { analysis::default_radius(BitTable::new().len(), hard_locations()) } else { radius };
+        Box::into_raw(Box::new(KanervaSdm(Memory::new(seed, r))))
+    }).unwrap_or(ptr::null_mut())
+}
+///
+/// Release a memory; `NULL` is ignored.
+///
+#[no_mangle]
+pub unsafe extern "C" fn kanerva_sdm_free(sdm: *mut KanervaSdm) {
+    if !sdm.is_null() { drop(Box::from_raw(sdm)); }
+}
+///
+/// Activation radius of the memory, `0` for `NULL`.
+///
+#[no_mangle]
+pub unsafe extern "C" fn kanerva_sdm_radius(sdm: *const KanervaSdm) -> size_t {
+    if sdm.is_null() { 0 } else { (*sdm).0.radius() }
+}
+///
+/// Write `data` at `address`, both of `len` bytes.
+///
+#[no_mangle]
+pub unsafe extern "C" fn kanerva_sdm_write(sdm: *mut KanervaSdm, address: *const u8, data: *const u8, len: size_t) -> c_int {
+    if sdm.is_null() || address.is_null() || data.is_null() { return KANERVA_ERR_NULL; }
+    let (address, data) = match (from_bytes(slice::from_raw_parts(address, len)), from_bytes(slice::from_raw_parts(data, len))) {
+        (Ok(a), Ok(d)) => (a, d),
+        _ => return KANERVA_ERR_LENGTH,
+    };
+    guard(|| { (*sdm).0.write(&address, &data); KANERVA_OK })
+}
+///
+/// Read at `cue` into `out`, both of `len` bytes.
+///
+#[no_mangle]
+pub unsafe extern "C" fn kanerva_sdm_read(sdm: *mut KanervaSdm, cue: *const u8, out: *mut u8, len: size_t) -> c_int {
+    if sdm.is_null() || cue.is_null() || out.is_null() { return KANERVA_ERR_NULL; }
+    let cue = match from_bytes(slice::from_raw_parts(cue, len)) { Ok(c) => c, Err(_) => return KANERVA_ERR_LENGTH };
+    let out = slice::from_raw_parts_mut(out, len);
+    guard(|| { out.copy_from_slice(&to_bytes(&(*sdm).0.read(&cue))); KANERVA_OK })
+}
+///
+/// Save the memory to `path`.
+///
+#[no_mangle]
+pub unsafe extern "C" fn kanerva_sdm_save(sdm: *const KanervaSdm, p: *const c_char) -> c_int {
+    if sdm.is_null() { return KANERVA_ERR_NULL; }
+    let p = match path(p) { Ok(p) => p, Err(e) => return e };
+    guard(|| match (*sdm).0.save(p) { Ok(()) => KANERVA_OK, Err(_) => KANERVA_ERR_IO })
+}
+///
+/// Load the memory saved at `path` into `*out`.
+///
+#[no_mangle]
+pub unsafe extern "C" fn kanerva_sdm_load(p: *const c_char, out: *mut *mut KanervaSdm) -> c_int {
+    if out.is_null() { return KANERVA_ERR_NULL; }
+    *out = ptr::null_mut();
+    let p = match path(p) { Ok(p) => p, Err(e) => return e };
+    guard(|| match Memory::load(p) {
+        Ok(m) => { *out = Box::into_raw(Box::new(KanervaSdm(m))); KANERVA_OK },
+        Err(ref e) if e.kind() == ErrorKind::InvalidData || e.kind() == ErrorKind::UnexpectedEof => KANERVA_ERR_FORMAT,
+        Err(_) => KANERVA_ERR_IO,
+    })
+}
+///
+/// Static description of an error code.
+///
+#[no_mangle]
+pub extern "C" fn kanerva_strerror(code: c_int) -> *const c_char {
+    let s: &'static [u8] = match code {
+        KANERVA_OK => b"success\0",
+        KANERVA_ERR_NULL => b"null pointer\0",
+        KANERVA_ERR_LENGTH => b"wrong buffer length\0",
+        KANERVA_ERR_IO => b"i/o error\0",
+        KANERVA_ERR_FORMAT => b"not a memory of this build\0",
+        KANERVA_ERR_PATH => b"path is not valid UTF-8\0",
+        KANERVA_ERR_PANIC => b"internal error\0",
+        _ => b"unknown error\0",
+    };
+    s.as_ptr() as *const c_char
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env;
+    use std::ffi::{CStr, CString};
+    use std::fs;
+    use std::process;
+    use std::ptr;
+    use super::*;
+    use vsa;
+
+    #[test]
+    fn test_capi_write_read_save_load() {
+        unsafe {
+            let sdm = kanerva_sdm_new(1, 0);
+            assert!(!sdm.is_null());
+            assert!(kanerva_sdm_radius(sdm) > 0);
+            let n = kanerva_address_bytes();
+            let x = to_bytes(&vsa::random(2));
+            let mut out = vec![0u8; n];
+            assert_eq!(kanerva_sdm_write(sdm, x.as_ptr(), x.as_ptr(), n), KANERVA_OK);
+            assert_eq!(kanerva_sdm_read(sdm, x.as_ptr(), out.as_mut_ptr(), n), KANERVA_OK);
+            assert_eq!(out, x);
+
+            let path = env::temp_dir().join(format!("kanerva-capi-{}.sdm", process::id()));
+            let cpath = CString::new(path.to_str().unwrap()).unwrap();
+            assert_eq!(kanerva_sdm_save(sdm, cpath.as_ptr()), KANERVA_OK);
+            let mut loaded: *mut KanervaSdm = ptr::null_mut();
+            assert_eq!(kanerva_sdm_load(cpath.as_ptr(), &mut loaded), KANERVA_OK);
+            let mut out2 = vec![0u8; n];
+            assert_eq!(kanerva_sdm_read(loaded, x.as_ptr(), out2.as_mut_ptr(), n), KANERVA_OK);
+            assert_eq!(out2, x);
+            fs::remove_file(&path).unwrap();
+            kanerva_sdm_free(loaded);
+            kanerva_sdm_free(sdm);
+        }
+    }
+
+    #[test]
+    fn test_capi_errors() {
+        unsafe {
+            let n = kanerva_address_bytes();
+            let x = vec![0u8; n];
+            let mut out = vec![0u8; n];
+            assert_eq!(kanerva_sdm_write(ptr::null_mut(), x.as_ptr(), x.as_ptr(), n), KANERVA_ERR_NULL);
+            let sdm = kanerva_sdm_new(2, 0);
+            assert_eq!(kanerva_sdm_read(sdm, x.as_ptr(), out.as_mut_ptr(), n - 1), KANERVA_ERR_LENGTH);
+            let missing = CString::new("/nonexistent/kanerva.sdm").unwrap();
+            let mut loaded: *mut KanervaSdm = ptr::null_mut();
+            assert_eq!(kanerva_sdm_load(missing.as_ptr(), &mut loaded), KANERVA_ERR_IO);
+            assert!(loaded.is_null());
+            assert_eq!(kanerva_sdm_load(ptr::null(), &mut loaded), KANERVA_ERR_NULL);
+            kanerva_sdm_free(sdm);
+            kanerva_sdm_free(ptr::null_mut());
+            assert_eq!(CStr::from_ptr(kanerva_strerror(KANERVA_ERR_LENGTH)).to_str().unwrap(), "wrong buffer length");
+        }
+    }
+}
@@ -7,18 +7,18 @@ include!(concat!(env!("OUT_DIR"), "/dimensions.rs"));
 ///
 use std::mem;
 ///
-use bittable::BitTable;
+use hypervector::HyperVector;
 ///
 /// Number of bits: in a `usize` (`64` in a 64 bits target).
 ///
 #[inline(always)]
 const fn bits() -> usize { mem::size_of::<usize>() * 8 }
-/// Compute the position for `DIM*ADD_DIM` elements where
+/// Compute the position for `row*ADD_DIM` elements where
 /// `index` in `0..ADD_DIM`
-/// `i` in `0..DIM*bits()`
+/// `i` in `0..row`
 #[inline(always)]
-fn pos(index: usize, i: usize) -> usize {
-    let pos = (DIM*bits())*index+i;
+fn pos(row: usize, index: usize, i: usize) -> usize {
+    let pos = row*index+i;
     pos
 }
 ///
@@ -44,41 +44,104 @@ impl Counter {
 	/// 
 	///
 	pub fn new() -> Self {
-		let v: Vec<i16>=vec![0; DIM*bits()*ADD_DIM]; 
+		Counter::with_bits(DIM*bits())
+	}
+	///
+	/// Zero counters for addresses of `bits` bits: `bits` counters per hard location.
+	///
+	pub fn with_bits(bits: usize) -> Self {
+		assert!(bits > 0, "addresses of at least one bit");
+		Counter(vec![0; bits*ADD_DIM])
+	}
+	///
+	/// Build counters from `ADD_DIM` rows of equal length, row by row.
+	///
+	pub fn from_vec(v: Vec<i16>) -> Self {
+		assert!(!v.is_empty() && v.len() % ADD_DIM == 0, "{} counters for {} hard locations", v.len(), ADD_DIM);
 		Counter(v)
 	}
 	///
+	/// Number of counters of a hard location, one per bit of the addresses.
+	///
+	pub fn bits(&self) -> usize { self.0.len() / ADD_DIM }
+	///
 	///
 	///
 	pub fn len(&self) -> usize { self.0.len() }
+	///
+	/// All the counter values, row by row.
+	///
+	pub fn as_slice(&self) -> &[i16] { &self.0 }
 	/// Get counter value for address index, at position i.
 	/// `index` in `ADD_DIM`
 	/// `i` in `DIM`
 	pub fn get(&self, index: usize, i: usize) -> i16 {
-		self.0[pos(index,i)]
+		self.0[pos(self.bits(),index,i)]
 	} 
 	/// Set counter value for address index, at position i.
 	/// `index` is in `0..ADD_DIM`
 	/// `i` in `0..DIM`
+	/// Saturates at the bounds of `i16`.
 	pub fn set(&mut self, index: usize, i: usize, b: bool) {
-		if b { self.0[pos(index,i)] += 1; } else { self.0[pos(index,i)] -= 1; }
+		let p = pos(self.bits(),index,i);
+		Counter::update(&mut self.0[p], b);
 	} 
 	///
+	/// Add `1`, or `-1` if `!b`, to the counter `x`, saturating at the bounds of `i16`.
+	///
+	pub fn update(x: &mut i16, b: bool) {
+		*x = if b { x.saturating_add(1) } else { x.saturating_sub(1) };
+	}
+	///
 	/// Update the counters at address `index` using input `b`.
 	///
-	pub fn input(&mut self, index: usize, b: &BitTable) {
-		for i in 0..DIM*bits() { 
-			if b.get(i) { self.set(index, i, true); } 
-			else { self.set(index, i, false); } 
-		};
+	pub fn input<V: HyperVector>(&mut self, index: usize, b: &V) {
+		let row = self.bits();
+		Counter::input_row(&mut self.0[pos(row,index,0)..pos(row,index+1,0)], b);
+	}
+	///
+	/// Number of counters of a hard location of `Counter::new`, for `BitTable` addresses.
+	///
+	pub fn row() -> usize { DIM*bits() }
+	///
+	/// Update the counters `row` of one hard location using input `b`, as `input`;
+	/// counters saturate at the bounds of `i16`.
+	///
+	/// For memories keeping their counters outside a `Counter`.
+	///
+	pub fn input_row<V: HyperVector>(row: &mut [i16], b: &V) {
+		for (i, x) in row.iter_mut().enumerate() { Counter::update(x, b.get(i)); }
 	}
 	///
 	///
 	///
-	pub fn get_counters(&mut self, index: usize) -> Vec<i16> {
-		let mut res: Vec<i16> = vec![0; DIM*bits()];
-		for i in pos(index,0)..(pos(index,0)+DIM*bits()) { res[i-pos(index,0)] = self.0[i]; }
-		res
+	pub fn get_counters(&self, index: usize) -> Vec<i16> {
+		self.0[pos(self.bits(),index,0)..pos(self.bits(),index+1,0)].to_vec()
+	}
+	///
+	/// Add the counters of `other`, saturating at the bounds of `i16`.
+	///
+	/// Returns the number of counters that saturated.
+	///
+	pub fn merge(&mut self, other: &Counter) -> usize {
+		assert_eq!(self.bits(), other.bits(), "merge of counters of different lengths");
+		(0..ADD_DIM).map(|index| self.merge_row(index, &other.0[pos(other.bits(),index,0)..pos(other.bits(),index+1,0)])).sum()
+	}
+	///
+	/// Add `row` to the counters at address `index`, saturating at the bounds of `i16`.
+	///
+	/// Returns the number of counters that saturated.
+	///
+	pub fn merge_row(&mut self, index: usize, row: &[i16]) -> usize {
+		let mut saturated = 0;
+		let n = self.bits();
+		for (x, &y) in self.0[pos(n,index,0)..pos(n,index+1,0)].iter_mut().zip(row.iter()) {
+			match x.checked_add(y) {
+				Some(z) => *x = z,
+				None => { *x = x.saturating_add(y); saturated += 1; },
+			}
+		}
+		saturated
 	}
 	///
 	/// Print the content. 
@@ -86,7 +149,7 @@ impl Counter {
 	pub fn show(& self) {
 		print!("\nC[\n");
 		for index in 0..ADD_DIM { 
-			for i in 0..(DIM*bits()) { print!("{},", self.get(index,i)); }
+			for i in 0..self.bits() { print!("{},", self.get(index,i)); }
     		println!();
     	}
     	print!("]\n");
@@ -100,7 +163,7 @@ mod tests {
 	use std::mem;
 	use super::{pos, bits};
     use super::Counter;
-    use super::BitTable;
+    use bittable::BitTable;
     use super::{ADD_DIM, DIM};
 
     #[test]
@@ -114,7 +177,7 @@ mod tests {
     	let mut v: usize;
     	let _: Counter = Counter::new();  
     	for index in 0..ADD_DIM {
-			v=pos(index, 0);
+			v=pos(DIM*bits(), index, 0);
 			assert_eq!(v, index*(DIM*bits()));
 		}
     } 
@@ -143,13 +206,23 @@ mod tests {
 
     #[test]
 	fn test_counter_get_counters() {
-		let mut cont: Counter = Counter::new(); 
+		let cont: Counter = Counter::new(); 
 		let mut v: Vec<i16>;
 		for index in 0..ADD_DIM {
 			v = cont.get_counters(index);
 		}
 	}
 
+    #[test]
+    fn test_counter_merge() {
+    	let mut v = vec![0i16; DIM*bits()*ADD_DIM];
+    	v[0] = i16::max_value() - 1;
+    	v[1] = -3;
+    	let mut c = Counter::from_vec(v.clone());
+    	assert_eq!(c.merge(&Counter::from_vec(v)), 1);
+    	assert_eq!((c.get(0, 0), c.get(0, 1), c.get(0, 2)), (i16::max_value(), -6, 0));
+    }
+
     #[test]
     fn test_counter_input() { 
     	let bt = BitTable::new_rand();
@@ -158,4 +231,32 @@ mod tests {
 		for index in 0..ADD_DIM { c.input(index, &bt); }
 		//c.show();
     }
+
+    #[test]
+    fn test_counter_with_bits() {
+    	let mut c = Counter::with_bits(1000);
+    	assert_eq!((c.bits(), c.len()), (1000, 1000*ADD_DIM));
+    	assert_eq!(Counter::new().bits(), Counter::row());
+    	c.set(ADD_DIM-1, 999, true);
+    	assert_eq!(c.get_counters(ADD_DIM-1)[999], 1);
+    	assert_eq!(c.as_slice()[1000*ADD_DIM-1], 1);
+    	assert_eq!(Counter::from_vec(c.as_slice().to_vec()).bits(), 1000);
+    }
+
+    #[test]
+    fn test_counter_saturates() {
+    	let mut v = vec![0i16; DIM*bits()*ADD_DIM];
+    	v[0] = i16::max_value();
+    	v[1] = i16::min_value();
+    	let mut c = Counter::from_vec(v);
+    	c.set(0, 0, true);
+    	c.set(0, 1, false);
+    	assert_eq!((c.get(0, 0), c.get(0, 1)), (i16::max_value(), i16::min_value()));
+    	let mut row = vec![i16::max_value(); Counter::row()];
+    	row[1] = i16::min_value();
+    	let mut b = BitTable::new();
+    	b.set(0);
+    	Counter::input_row(&mut row, &b);
+    	assert_eq!((row[0], row[1], row[2]), (i16::max_value(), i16::min_value(), i16::max_value() - 1));
+    }
 }
@@ -0,0 +1,252 @@
+//
+// Snapshots and copy-on-write forks of an SDM.
+//
+// The counters of a `CowSdm` are split into pages of `PAGE_LOCATIONS` hard locations,
+// each behind an `Arc`, and the address space is shared by all the forks. A `fork` or a
+// `snapshot` only copies the page pointers; a write copies the pages it changes if they
+// are still shared (`Arc::make_mut`), so a fork costs memory in proportion to what is
+// written after it.
+//
+// Pages shared by two forks are known to be equal, so `diff` only compares the pages
+// that were copied since the forks diverged.
+//
+#[allow(missing_docs)]
+include!(concat!(env!("OUT_DIR"), "/dimensions.rs"));
+///
+use std::sync::Arc;
+///
+use bittable::BitTable;
+///
+use address_space::AddressSpace;
+///
+use counter::Counter;
+///
+use {threshold, SDM};
+///
+/// Hard locations per counter page: 16 locations of 128 counters are 4 KiB.
+///
+pub const PAGE_LOCATIONS: usize = 16;
+///
+/// An SDM that can be forked and snapshotted without copying its counters.
+///
+#[derive(Clone, Debug)]
+pub struct CowSdm {
+    a: Arc<AddressSpace>,
+    pages: Vec<Arc<Vec<i16>>>,
+}
+///
+/// A read-only state of a `CowSdm`, cheap to keep and to fork.
+///
+#[derive(Clone, Debug)]
+pub struct Snapshot(CowSdm);
+///
+/// Difference between the counters of two memories.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diff {
+    ///
+    /// Hard locations with at least one different counter, in increasing order.
+    ///
+    pub locations: Vec<usize>,
+    ///
+    /// Number of different counters.
+    ///
+    pub counters: usize,
+    ///
+    /// Sum of the absolute differences of the counters.
+    ///
+    pub total: u64,
+}
+///
+///
+///
+impl Diff {
+    ///
+    /// Whether the counters are equal.
+    ///
+    pub fn is_empty(&self) -> bool { self.counters == 0 }
+}
+///
+///
+///
+impl CowSdm {
+    ///
+    /// Page the counters of `sdm`.
+    ///
+    pub fn new(sdm: SDM) -> Self {
+        let pages = sdm.counter().as_slice().chunks(PAGE_LOCATIONS * Counter::row()).map(|p| Arc::new(p.to_vec())).collect();
+        CowSdm { a: Arc::new(sdm.address_space().clone()), pages }
+    }
+    ///
+    ///
+    ///
+    pub fn address_space(&self) -> &AddressSpace { &self.a }
+    ///
+    /// The current state, sharing every page with this memory.
+    ///
+    pub fn snapshot(&self) -> Snapshot { Snapshot(self.clone()) }
+    ///
+    /// A new branch, sharing every page with this memory until either is written.
+    ///
+    pub fn fork(&self) -> CowSdm { self.clone() }
+    ///
+    /// Number of counter pages.
+    ///
+    pub fn pages(&self) -> usize { self.pages.len() }
+    ///
+    /// Number of pages shared with `other`.
+    ///
+    pub fn shared_pages(&self, other: &CowSdm) -> usize {
+        self.pages.iter().zip(other.pages.iter()).filter(|&(p, q)| Arc::ptr_eq(p, q)).count()
+    }
+    ///
+    /// Write `data` at the hard locations activated by `address`, copying the shared
+    /// pages it changes.
+    ///
+    pub fn write_data(&mut self, address: &BitTable, data: &BitTable, radius: usize) {
+        let selected = self.a.radius_fit(address, radius);
+        let n = Counter::row();
+        for i in (0..ADD_DIM).filter(|&i| selected[i] != 0) {
+            let page = Arc::make_mut(&mut self.pages[i / PAGE_LOCATIONS]);
+            let j = i % PAGE_LOCATIONS;
+            Counter::input_row(&mut page[j * n..(j + 1) * n], data);
+        }
+    }
+    ///
+    /// Write `b` at itself, copying the shared pages it changes.
+    ///
+    pub fn write(&mut self, b: &BitTable, radius: usize) {
+        self.write_data(b, b, radius)
+    }
+    ///
+    /// Read at `cue`; no page is copied.
+    ///
+    pub fn read(&self, cue: &BitTable, radius: usize) -> BitTable {
+        let selected = self.a.radius_fit(cue, radius);
+        let n = Counter::row();
+        let mut sum: Vec<i32> = vec![0; n];
+        for i in (0..ADD_DIM).filter(|&i| selected[i] != 0) {
+            let j = i % PAGE_LOCATIONS;
+            let counters = &self.pages[i / PAGE_LOCATIONS][j * n..(j + 1) * n];
+            for (total, &x) in sum.iter_mut().zip(counters.iter()) { *total += i32::from(x); }
+        }
+        threshold(&sum)
+    }
+    ///
+    /// Reset all the counters; the pages are released, not copied.
+    ///
+    pub fn clear(&mut self) {
+        for p in self.pages.iter_mut() { *p = Arc::new(vec![0; p.len()]); }
+    }
+    ///
+    /// Difference between the counters of this memory and of `other`.
+    ///
+    /// Panics if the memories do not have the same hard locations.
+    ///
+    pub fn diff(&self, other: &CowSdm) -> Diff {
+        assert!(Arc::ptr_eq(&self.a, &other.a) || (0..ADD_DIM).all(|i| self.a.get(i) == other.a.get(i)),
+            "diff of memories with different hard locations");
+        let n = Counter::row();
+        let mut diff = Diff::default();
+        for (k, (p, q)) in self.pages.iter().zip(other.pages.iter()).enumerate() {
+            if Arc::ptr_eq(p, q) { continue; }
+            for (j, (r, s)) in p.chunks(n).zip(q.chunks(n)).enumerate() {
+                let mut changed = false;
+                for (&x, &y) in r.iter().zip(s.iter()).filter(|&(x, y)| x != y) {
+                    changed = true;
+                    diff.counters += 1;
+                    diff.total += (i32::from(x) - i32::from(y)).abs() as u64;
+                }
+                if changed { diff.locations.push(k * PAGE_LOCATIONS + j); }
+            }
+        }
+        diff
+    }
+    ///
+    /// Copy of the memory as a plain `SDM`.
+    ///
+    pub fn to_sdm(&self) -> SDM {
+        let mut c: Vec<i16> = Vec::with_capacity(ADD_DIM * Counter::row());
+        for p in self.pages.iter() { c.extend_from_slice(p); }
+        SDM::from_parts((*self.a).clone(), Counter::from_vec(c))
+    }
+}
+///
+///
+///
+impl Snapshot {
+    ///
+    /// A writable branch starting from the snapshot.
+    ///
+    pub fn fork(&self) -> CowSdm { self.0.clone() }
+    ///
+    /// Read at `cue` in the state of the snapshot.
+    ///
+    pub fn read(&self, cue: &BitTable, radius: usize) -> BitTable { self.0.read(cue, radius) }
+    ///
+    /// Difference between the counters of the snapshot and of `other`.
+    ///
+    pub fn diff(&self, other: &CowSdm) -> Diff { self.0.diff(other) }
+    ///
+    /// Copy of the snapshot as a plain `SDM`.
+    ///
+    pub fn to_sdm(&self) -> SDM { self.0.to_sdm() }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use vsa;
+
+    const RADIUS: usize = 54;
+
+    fn trained() -> CowSdm {
+        let mut m = CowSdm::new(SDM::from_parts(AddressSpace::with_seed(2), Counter::new()));
+        for k in 0..5 { m.write(&vsa::random(k), RADIUS); }
+        m
+    }
+
+    #[test]
+    fn test_cow_matches_sdm() {
+        let mut plain = SDM::from_parts(AddressSpace::with_seed(2), Counter::new());
+        for k in 0..5 { plain.write(&vsa::random(k), RADIUS); }
+        let m = trained();
+        assert_eq!(m.pages(), (ADD_DIM + PAGE_LOCATIONS - 1) / PAGE_LOCATIONS);
+        assert_eq!(m.to_sdm().counter().as_slice(), plain.counter().as_slice());
+        assert_eq!(m.read(&vsa::random(2), RADIUS), vsa::random(2));
+    }
+
+    #[test]
+    fn test_cow_fork_shares_pages() {
+        let base = trained();
+        let mut branch = base.fork();
+        assert_eq!(branch.shared_pages(&base), base.pages());
+        let x = vsa::random(100);
+        branch.write(&x, RADIUS);
+        let copied = base.pages() - branch.shared_pages(&base);
+        let activated = base.address_space().radius_fit(&x, RADIUS).iter().filter(|&&s| s != 0).count();
+        assert!(copied > 0 && copied <= activated);
+        assert_eq!(branch.read(&x, RADIUS), x);
+        assert!(base.diff(&trained()).is_empty());
+    }
+
+    #[test]
+    fn test_cow_snapshot_diff() {
+        let mut m = trained();
+        let snapshot = m.snapshot();
+        let x = vsa::random(101);
+        m.write(&x, RADIUS);
+        let diff = snapshot.diff(&m);
+        assert_eq!(diff.locations, m.address_space().radius_fit(&x, RADIUS).iter()
+            .enumerate().filter(|&(_, &s)| s != 0).map(|(i, _)| i).collect::<Vec<usize>>());
+        assert_eq!(diff.counters, diff.locations.len() * x.len());
+        assert_eq!(diff.total, diff.counters as u64);
+        assert_eq!(m.diff(&snapshot.fork()), diff);
+        // The snapshot still reads the state before the write.
+        assert_eq!(snapshot.to_sdm().counter().as_slice(), trained().to_sdm().counter().as_slice());
+        m.clear();
+        assert!(m.to_sdm().counter().as_slice().iter().all(|&c| c == 0));
+        assert!(!snapshot.to_sdm().counter().as_slice().iter().all(|&c| c == 0));
+    }
+}
@@ -0,0 +1,684 @@
+//
+// SDM sharded across processes.
+//
+// The hard locations are split into ranges of consecutive locations, each held by a
+// `Shard` with its addresses and counters. Every shard draws the whole address space
+// from the same seed and keeps only its range, so the shards of one memory agree on
+// the hard locations without exchanging them.
+//
+// A `ShardedSdm` talks to its shards through a `Transport`: `LocalTransport` calls an
+// in-process shard, `TcpTransport` a shard served by `serve` in another process (or
+// thread, over loopback). A write is sent to every shard; a read collects from every
+// shard the sum of its activated counters, adds the partial sums and thresholds the
+// result as `SDM::read`. Requests are sent to all the shards before any reply is read,
+// so the shards work in parallel.
+//
+// The protocol is little-endian binary, one request then one reply per exchange:
+//
+//     request:  u32 kind (1 info, 2 write, 3 read, 4 clear)
+//               write: u32 radius, address words, data words (as the memory files)
+//               read:  u32 radius, cue words
+//     reply:    info:  u64 seed, u64 first location, u64 end location, u32 bits
+//               write, clear: u32 0
+//               read:  u32 activated locations, one i32 sum per bit
+//
+#[allow(missing_docs)]
+include!(concat!(env!("OUT_DIR"), "/dimensions.rs"));
+///
+use std::collections::VecDeque;
+///
+use std::fmt;
+///
+use std::io::{self, BufReader, BufWriter, Read, Write};
+///
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+///
+use std::ops::Range;
+///
+use std::sync::{Arc, Mutex};
+///
+use std::thread;
+///
+use bittable::BitTable;
+///
+use address_space::AddressSpace;
+///
+use counter::Counter;
+///
+use interop::{from_words, read_i32, read_u32, read_u64, to_words, write_i32, write_u32, write_u64};
+///
+use threshold;
+///
+const INFO: u32 = 1;
+///
+const WRITE: u32 = 2;
+///
+const READ: u32 = 3;
+///
+const CLEAR: u32 = 4;
+///
+///
+///
+fn invalid(msg: String) -> io::Error { io::Error::new(io::ErrorKind::InvalidData, msg) }
+///
+/// Hard locations held by shard `shard` of `shards`: consecutive ranges that differ in
+/// length by at most one.
+///
+pub fn shard_range(shard: usize, shards: usize) -> Range<usize> {
+    assert!(shard < shards, "shard {} of {}", shard, shards);
+    shard * ADD_DIM / shards..(shard + 1) * ADD_DIM / shards
+}
+///
+/// Sum of the counters of the activated hard locations, for each bit.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartialSum {
+    ///
+    ///
+    ///
+    pub sums: Vec<i32>,
+    ///
+    /// Number of activated hard locations.
+    ///
+    pub activated: usize,
+}
+///
+///
+///
+impl PartialSum {
+    ///
+    /// The sum over no hard location.
+    ///
+    pub fn zero() -> Self { PartialSum { sums: vec![0; Counter::row()], activated: 0 } }
+    ///
+    /// Add the sum of other hard locations.
+    ///
+    pub fn add(&mut self, other: &PartialSum) {
+        for (s, &x) in self.sums.iter_mut().zip(other.sums.iter()) { *s += x; }
+        self.activated += other.activated;
+    }
+}
+///
+/// Request sent to a shard.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum Request {
+    ///
+    /// The seed and the range of hard locations of the shard.
+    ///
+    Info,
+    ///
+    /// Write `data` at the hard locations of the shard activated by `address`.
+    ///
+    Write {
+        ///
+        address: BitTable,
+        ///
+        data: BitTable,
+        ///
+        radius: usize,
+    },
+    ///
+    /// Sum the counters of the hard locations of the shard activated by `cue`.
+    ///
+    Read {
+        ///
+        cue: BitTable,
+        ///
+        radius: usize,
+    },
+    ///
+    /// Reset the counters of the shard.
+    ///
+    Clear,
+}
+///
+/// Reply of a shard.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reply {
+    ///
+    /// Reply to `Request::Info`.
+    ///
+    Info {
+        ///
+        seed: u64,
+        ///
+        locations: Range<usize>,
+    },
+    ///
+    /// Reply to `Request::Write` and `Request::Clear`.
+    ///
+    Done,
+    ///
+    /// Reply to `Request::Read`.
+    ///
+    Sum(PartialSum),
+}
+///
+///
+///
+fn read_table<R: Read>(r: &mut R) -> io::Result<BitTable> {
+    let words = (Counter::row() + 63) / 64;
+    let mut w: Vec<u64> = Vec::with_capacity(words);
+    for _ in 0..words { w.push(read_u64(r)?); }
+    Ok(from_words(&w, Counter::row()))
+}
+///
+///
+///
+fn write_table<W: Write>(w: &mut W, bt: &BitTable) -> io::Result<()> {
+    for word in to_words(bt) { write_u64(w, word)?; }
+    Ok(())
+}
+///
+///
+///
+impl Request {
+    ///
+    /// Read a request, or `None` at the end of the stream.
+    ///
+    pub fn read_from<R: Read>(r: &mut R) -> io::Result<Option<Request>> {
+        let kind = match read_u32(r) {
+            Ok(kind) => kind,
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let request = match kind {
+            INFO => Request::Info,
+            WRITE => {
+                let radius = read_u32(r)? as usize;
+                let address = read_table(r)?;
+                Request::Write { address, data: read_table(r)?, radius }
+            },
+            READ => {
+                let radius = read_u32(r)? as usize;
+                Request::Read { cue: read_table(r)?, radius }
+            },
+            CLEAR => Request::Clear,
+            _ => return Err(invalid(format!("unknown request {}", kind))),
+        };
+        Ok(Some(request))
+    }
+    ///
+    ///
+    ///
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match *self {
+            Request::Info => write_u32(w, INFO),
+            Request::Write { ref address, ref data, radius } => {
+                write_u32(w, WRITE)?;
+                write_u32(w, radius as u32)?;
+                write_table(w, address)?;
+                write_table(w, data)
+            },
+            Request::Read { ref cue, radius } => {
+                write_u32(w, READ)?;
+                write_u32(w, radius as u32)?;
+                write_table(w, cue)
+            },
+            Request::Clear => write_u32(w, CLEAR),
+        }
+    }
+}
+///
+///
+///
+impl Reply {
+    ///
+    /// Read the reply to `request`.
+    ///
+    pub fn read_from<R: Read>(r: &mut R, request: &Request) -> io::Result<Reply> {
+        match *request {
+            Request::Info => {
+                let seed = read_u64(r)?;
+                let first = read_u64(r)? as usize;
+                let end = read_u64(r)? as usize;
+                let bits = read_u32(r)? as usize;
+                if bits != Counter::row() {
+                    return Err(invalid(format!("shard has {} bits per address, this build expects {}", bits, Counter::row())));
+                }
+                Ok(Reply::Info { seed, locations: first..end })
+            },
+            Request::Write { .. } | Request::Clear => match read_u32(r)? {
+                0 => Ok(Reply::Done),
+                status => Err(invalid(format!("shard failed with status {}", status))),
+            },
+            Request::Read { .. } => {
+                let activated = read_u32(r)? as usize;
+                let mut sums: Vec<i32> = Vec::with_capacity(Counter::row());
+                for _ in 0..Counter::row() { sums.push(read_i32(r)?); }
+                Ok(Reply::Sum(PartialSum { sums, activated }))
+            },
+        }
+    }
+    ///
+    ///
+    ///
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match *self {
+            Reply::Info { seed, ref locations } => {
+                write_u64(w, seed)?;
+                write_u64(w, locations.start as u64)?;
+                write_u64(w, locations.end as u64)?;
+                write_u32(w, Counter::row() as u32)
+            },
+            Reply::Done => write_u32(w, 0),
+            Reply::Sum(ref sum) => {
+                write_u32(w, sum.activated as u32)?;
+                for &x in sum.sums.iter() { write_i32(w, x)?; }
+                Ok(())
+            },
+        }
+    }
+}
+///
+/// A range of hard locations of a memory, with their counters.
+///
+#[derive(Clone, Debug)]
+pub struct Shard {
+    seed: u64,
+    first: usize,
+    a: Vec<BitTable>,
+    c: Vec<i16>,
+}
+///
+///
+///
+impl Shard {
+    ///
+    /// Empty counters for the hard `locations` of the address space drawn from `seed`.
+    ///
+    pub fn new(seed: u64, locations: Range<usize>) -> Self {
+        assert!(locations.start <= locations.end && locations.end <= ADD_DIM, "locations {:?} out of range", locations);
+        let all = AddressSpace::with_seed(seed);
+        let a: Vec<BitTable> = locations.clone().map(|i| all.get(i).clone()).collect();
+        Shard { seed, first: locations.start, c: vec![0; a.len() * Counter::row()], a }
+    }
+    ///
+    ///
+    ///
+    pub fn seed(&self) -> u64 { self.seed }
+    ///
+    /// The hard locations held by the shard.
+    ///
+    pub fn locations(&self) -> Range<usize> { self.first..self.first + self.a.len() }
+    ///
+    /// Counters of the shard, location by location.
+    ///
+    pub fn counters(&self) -> &[i16] { &self.c }
+    ///
+    /// Write `data` at the hard locations of the shard activated by `address`.
+    ///
+    pub fn write_data(&mut self, address: &BitTable, data: &BitTable, radius: usize) {
+        let n = Counter::row();
+        for (j, _) in self.a.iter().enumerate().filter(|&(_, h)| address.distance(h) <= radius) {
+            Counter::input_row(&mut self.c[j * n..(j + 1) * n], data);
+        }
+    }
+    ///
+    /// Sum the counters of the hard locations of the shard activated by `cue`.
+    ///
+    pub fn read_sum(&self, cue: &BitTable, radius: usize) -> PartialSum {
+        let n = Counter::row();
+        let mut sum = PartialSum::zero();
+        for (j, _) in self.a.iter().enumerate().filter(|&(_, h)| cue.distance(h) <= radius) {
+            for (s, &x) in sum.sums.iter_mut().zip(self.c[j * n..(j + 1) * n].iter()) { *s += i32::from(x); }
+            sum.activated += 1;
+        }
+        sum
+    }
+    ///
+    /// Reset every counter to `0`.
+    ///
+    pub fn clear(&mut self) {
+        for x in self.c.iter_mut() { *x = 0; }
+    }
+    ///
+    /// Carry out `request`.
+    ///
+    pub fn handle(&mut self, request: &Request) -> Reply {
+        match *request {
+            Request::Info => Reply::Info { seed: self.seed, locations: self.locations() },
+            Request::Write { ref address, ref data, radius } => { self.write_data(address, data, radius); Reply::Done },
+            Request::Read { ref cue, radius } => Reply::Sum(self.read_sum(cue, radius)),
+            Request::Clear => { self.clear(); Reply::Done },
+        }
+    }
+}
+///
+/// Connection to a shard. Replies come back in the order of the requests.
+///
+pub trait Transport: fmt::Debug + Send {
+    ///
+    /// Send `request`, without waiting for the reply.
+    ///
+    fn send(&mut self, request: &Request) -> io::Result<()>;
+    ///
+    /// Wait for the reply to the oldest request not yet answered.
+    ///
+    fn receive(&mut self) -> io::Result<Reply>;
+}
+///
+/// Transport to a shard in the same process.
+///
+#[derive(Debug)]
+pub struct LocalTransport {
+    shard: Shard,
+    replies: VecDeque<Reply>,
+}
+///
+///
+///
+impl LocalTransport {
+    ///
+    ///
+    ///
+    pub fn new(shard: Shard) -> Self { LocalTransport { shard, replies: VecDeque::new() } }
+    ///
+    ///
+    ///
+    pub fn shard(&self) -> &Shard { &self.shard }
+}
+///
+///
+///
+impl Transport for LocalTransport {
+    fn send(&mut self, request: &Request) -> io::Result<()> {
+        let reply = self.shard.handle(request);
+        self.replies.push_back(reply);
+        Ok(())
+    }
+    fn receive(&mut self) -> io::Result<Reply> {
+        self.replies.pop_front().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no request sent"))
+    }
+}
+///
+/// Transport to a shard served over TCP by `serve`.
+///
+#[derive(Debug)]
+pub struct TcpTransport {
+    r: BufReader<TcpStream>,
+    w: BufWriter<TcpStream>,
+    pending: VecDeque<Request>,
+}
+///
+///
+///
+impl TcpTransport {
+    ///
+    ///
+    ///
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(TcpTransport { r: BufReader::new(stream.try_clone()?), w: BufWriter::new(stream), pending: VecDeque::new() })
+    }
+}
+///
+///
+///
+impl Transport for TcpTransport {
+    fn send(&mut self, request: &Request) -> io::Result<()> {
+        request.write_to(&mut self.w)?;
+        self.w.flush()?;
+        self.pending.push_back(request.clone());
+        Ok(())
+    }
+    fn receive(&mut self) -> io::Result<Reply> {
+        let request = self.pending.pop_front()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no request sent"))?;
+        Reply::read_from(&mut self.r, &request)
+    }
+}
+///
+/// Answer the requests of one connection until it is closed.
+///
+fn answer(stream: TcpStream, shard: &Mutex<Shard>) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut r = BufReader::new(stream.try_clone()?);
+    let mut w = BufWriter::new(stream);
+    while let Some(request) = Request::read_from(&mut r)? {
+        let reply = shard.lock().unwrap_or_else(|e| e.into_inner()).handle(&request);
+        reply.write_to(&mut w)?;
+        w.flush()?;
+    }
+    Ok(())
+}
+///
+/// Serve `shard` to the connections accepted by `listener`, one thread per connection.
+///
+/// Only returns if accepting a connection fails.
+///
+pub fn serve(listener: TcpListener, shard: Shard) -> io::Result<()> {
+    let shard = Arc::new(Mutex::new(shard));
+    loop {
+        let (stream, peer) = listener.accept()?;
+        let shard = shard.clone();
+        thread::spawn(move || {
+            if let Err(e) = answer(stream, &shard) { eprintln!("kanerva: shard connection {}: {}", peer, e); }
+        });
+    }
+}
+///
+/// A memory whose hard locations are split between shards.
+///
+#[derive(Debug)]
+pub struct ShardedSdm {
+    seed: u64,
+    shards: Vec<Box<dyn Transport>>,
+}
+///
+///
+///
+impl ShardedSdm {
+    ///
+    /// Memory made of the shards behind `transports`, in any order.
+    ///
+    /// Fails unless the shards have the same seed and their ranges cover every hard
+    /// location exactly once.
+    ///
+    pub fn new(transports: Vec<Box<dyn Transport>>) -> io::Result<Self> {
+        let mut m = ShardedSdm { seed: 0, shards: transports };
+        if m.shards.is_empty() { return Err(io::Error::new(io::ErrorKind::InvalidInput, "no shard")); }
+        let mut ranges: Vec<(Range<usize>, u64, usize)> = Vec::with_capacity(m.shards.len());
+        for (k, reply) in m.exchange(&Request::Info)?.into_iter().enumerate() {
+            match reply {
+                Reply::Info { seed, locations } => ranges.push((locations, seed, k)),
+                other => return Err(invalid(format!("unexpected reply {:?}", other))),
+            }
+        }
+        ranges.sort_by_key(|r| (r.0.start, r.0.end));
+        let mut next = 0;
+        for &(ref locations, seed, k) in ranges.iter() {
+            if seed != ranges[0].1 {
+                return Err(invalid(format!("shard {} has seed {}, shard {} has seed {}", k, seed, ranges[0].2, ranges[0].1)));
+            }
+            if locations.start != next {
+                return Err(invalid(format!("shard {} holds locations {:?}, expected to start at {}", k, locations, next)));
+            }
+            next = locations.end;
+        }
+        if next != ADD_DIM { return Err(invalid(format!("locations {}..{} are not held by any shard", next, ADD_DIM))); }
+        m.seed = ranges[0].1;
+        let mut shards: Vec<Option<Box<dyn Transport>>> = m.shards.drain(..).map(Some).collect();
+        m.shards = ranges.iter().filter_map(|r| shards[r.2].take()).collect();
+        Ok(m)
+    }
+    ///
+    /// Memory drawn from `seed`, split into `shards` in-process shards.
+    ///
+    pub fn local(seed: u64, shards: usize) -> Self {
+        let mut transports: Vec<Box<dyn Transport>> = Vec::with_capacity(shards);
+        for k in 0..shards { transports.push(Box::new(LocalTransport::new(Shard::new(seed, shard_range(k, shards))))); }
+        ShardedSdm::new(transports).expect("in-process shards")
+    }
+    ///
+    /// Memory made of the shards served at `addrs`.
+    ///
+    pub fn connect<A: ToSocketAddrs>(addrs: &[A]) -> io::Result<Self> {
+        let mut transports: Vec<Box<dyn Transport>> = Vec::with_capacity(addrs.len());
+        for addr in addrs { transports.push(Box::new(TcpTransport::connect(addr)?)); }
+        ShardedSdm::new(transports)
+    }
+    ///
+    /// Seed of the address space.
+    ///
+    pub fn seed(&self) -> u64 { self.seed }
+    ///
+    ///
+    ///
+    pub fn shards(&self) -> usize { self.shards.len() }
+    ///
+    /// Send `request` to every shard, then collect the replies in the order of the shards.
+    ///
+    /// The replies of every shard that received the request are read even if another
+    /// shard failed, so that the connections stay in step.
+    ///
+    fn exchange(&mut self, request: &Request) -> io::Result<Vec<Reply>> {
+        let sent: Vec<io::Result<()>> = self.shards.iter_mut().map(|t| t.send(request)).collect();
+        let mut replies: Vec<Reply> = Vec::with_capacity(self.shards.len());
+        let mut failure: Option<io::Error> = None;
+        for (t, s) in self.shards.iter_mut().zip(sent.into_iter()) {
+            match s.and_then(|_| t.receive()) {
+                Ok(reply) => replies.push(reply),
+                Err(e) => if failure.is_none() { failure = Some(e); },
+            }
+        }
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(replies),
+        }
+    }
+    ///
+    /// Write `data` at the hard locations activated by `address`, on every shard.
+    ///
+    pub fn write_data(&mut self, address: &BitTable, data: &BitTable, radius: usize) -> io::Result<()> {
+        self.exchange(&Request::Write { address: address.clone(), data: data.clone(), radius })?;
+        Ok(())
+    }
+    ///
+    /// Write `b` at itself on every shard.
+    ///
+    pub fn write(&mut self, b: &BitTable, radius: usize) -> io::Result<()> {
+        self.write_data(b, b, radius)
+    }
+    ///
+    /// Sum of the partial sums of the shards at `cue`.
+    ///
+    pub fn read_sum(&mut self, cue: &BitTable, radius: usize) -> io::Result<PartialSum> {
+        let mut total = PartialSum::zero();
+        for reply in self.exchange(&Request::Read { cue: cue.clone(), radius })? {
+            match reply {
+                Reply::Sum(ref sum) => total.add(sum),
+                other => return Err(invalid(format!("unexpected reply {:?}", other))),
+            }
+        }
+        Ok(total)
+    }
+    ///
+    /// Read at `cue`, thresholding the sum of the shards as `SDM::read`.
+    ///
+    pub fn read(&mut self, cue: &BitTable, radius: usize) -> io::Result<BitTable> {
+        Ok(threshold(&self.read_sum(cue, radius)?.sums))
+    }
+    ///
+    /// Reset the counters of every shard.
+    ///
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.exchange(&Request::Clear)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::Cursor;
+    use super::*;
+    use vsa;
+    use SDM;
+
+    const RADIUS: usize = 54;
+
+    fn serve_loopback(shard: Shard) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || serve(listener, shard));
+        addr
+    }
+
+    #[test]
+    fn test_distributed_matches_sdm() {
+        let mut plain = SDM::from_parts(AddressSpace::with_seed(3), Counter::new());
+        let mut sharded = ShardedSdm::local(3, 4);
+        for k in 0..5 {
+            plain.write(&vsa::random(k), RADIUS);
+            sharded.write(&vsa::random(k), RADIUS).unwrap();
+        }
+        let x = vsa::random(2);
+        let sum = sharded.read_sum(&x, RADIUS).unwrap();
+        assert_eq!(sum.activated, plain.scan(&x, RADIUS).iter().filter(|&&s| s != 0).count());
+        let mut expected = vec![0i32; Counter::row()];
+        for i in (0..ADD_DIM).filter(|&i| plain.scan(&x, RADIUS)[i] != 0) {
+            for (e, c) in expected.iter_mut().zip(plain.counter().get_counters(i)) { *e += i32::from(c); }
+        }
+        assert_eq!(sum.sums, expected);
+        assert_eq!(sharded.read(&x, RADIUS).unwrap(), x);
+        sharded.clear().unwrap();
+        assert!(sharded.read_sum(&x, RADIUS).unwrap().sums.iter().all(|&s| s == 0));
+    }
+
+    #[test]
+    fn test_distributed_loopback() {
+        let addrs: Vec<String> = (0..3).map(|k| serve_loopback(Shard::new(5, shard_range(k, 3)))).collect();
+        // The shards may be listed in any order.
+        let mut remote = ShardedSdm::connect(&[&addrs[2], &addrs[0], &addrs[1]]).unwrap();
+        let mut local = ShardedSdm::local(5, 2);
+        assert_eq!((remote.seed(), remote.shards()), (5, 3));
+        for k in 0..5 {
+            remote.write_data(&vsa::random(k), &vsa::random(k + 10), RADIUS).unwrap();
+            local.write_data(&vsa::random(k), &vsa::random(k + 10), RADIUS).unwrap();
+        }
+        let x = vsa::random(3);
+        assert_eq!(remote.read_sum(&x, RADIUS).unwrap(), local.read_sum(&x, RADIUS).unwrap());
+        assert_eq!(remote.read(&x, RADIUS).unwrap(), vsa::random(13));
+    }
+
+    #[test]
+    fn test_distributed_rejects_bad_shards() {
+        let shards = |parts: Vec<(u64, Range<usize>)>| -> Vec<Box<dyn Transport>> {
+            let mut v: Vec<Box<dyn Transport>> = Vec::new();
+            for (s, r) in parts { v.push(Box::new(LocalTransport::new(Shard::new(s, r)))); }
+            v
+        };
+        assert!(ShardedSdm::new(shards(vec![])).is_err());
+        assert!(ShardedSdm::new(shards(vec![(1, 0..10), (1, 20..ADD_DIM)])).is_err());
+        assert!(ShardedSdm::new(shards(vec![(1, 0..20), (1, 10..ADD_DIM)])).is_err());
+        assert!(ShardedSdm::new(shards(vec![(1, 0..10)])).is_err());
+        assert!(ShardedSdm::new(shards(vec![(1, 0..10), (2, 10..ADD_DIM)])).is_err());
+        assert!(ShardedSdm::new(shards(vec![(1, 10..ADD_DIM), (1, 0..10)])).is_ok());
+    }
+
+    #[test]
+    fn test_distributed_protocol_round_trip() {
+        let requests = vec![
+            Request::Info,
+            Request::Write { address: vsa::random(1), data: vsa::random(2), radius: 50 },
+            Request::Read { cue: vsa::random(3), radius: 60 },
+            Request::Clear,
+        ];
+        let mut buf: Vec<u8> = Vec::new();
+        for r in requests.iter() { r.write_to(&mut buf).unwrap(); }
+        let mut cursor = Cursor::new(buf);
+        for r in requests.iter() { assert_eq!(Request::read_from(&mut cursor).unwrap().as_ref(), Some(r)); }
+        assert_eq!(Request::read_from(&mut cursor).unwrap(), None);
+        let mut shard = Shard::new(1, 0..ADD_DIM);
+        for r in requests.iter() {
+            let reply = shard.handle(r);
+            let mut buf: Vec<u8> = Vec::new();
+            reply.write_to(&mut buf).unwrap();
+            assert_eq!(Reply::read_from(&mut Cursor::new(buf), r).unwrap(), reply);
+        }
+    }
+}
@@ -0,0 +1,36 @@
+//!
+//! Encoders of values into `BitTable` addresses.
+//!
+//! Every encoder is seeded and maps nearby values to nearby addresses,
+//! so that a cue close to a stored value activates the same hard locations.
+//!
+///
+use rand::Rng;
+///
+use bittable::BitTable;
+///
+use noise::rng_from_seed;
+///
+/// Scalar, cyclic and multi-dimensional numeric encoders.
+///
+pub mod scalar;
+///
+/// Locality-sensitive hashing of dense embeddings.
+///
+pub mod simhash;
+///
+/// Character and n-gram encoders of text.
+///
+pub mod text;
+///
+/// A seeded random permutation of `0..n`.
+///
+pub fn permutation(n: usize, seed: u64) -> Vec<usize> {
+    let mut p: Vec<usize> = (0..n).collect();
+    rng_from_seed(seed).shuffle(&mut p);
+    p
+}
+///
+/// Number of bits of the encoded addresses.
+///
+pub fn address_bits() -> usize { BitTable::new().len() }
@@ -0,0 +1,268 @@
+//
+// Numeric encoders.
+//
+//   ThermometerEncoder: the first `level` bits set; distance proportional to the value gap.
+//   LevelEncoder:       random-flip level hypervectors; the extreme levels are orthogonal.
+//   CyclicEncoder:      periodic values (angles, time of day); wraps around the period.
+//   ProjectionEncoder:  vectors, by the sign of random projections; distance grows with the angle.
+//
+///
+use rand::Rng;
+///
+use rand::distributions::StandardNormal;
+///
+use bittable::BitTable;
+///
+use noise::rng_from_seed;
+///
+use vsa;
+///
+use super::{address_bits, permutation};
+///
+/// Position of `v` in `[min, max]`, clamped to `[0, 1]`.
+///
+fn unit(v: f64, min: f64, max: f64) -> f64 {
+    assert!(max > min, "empty range [{}, {}]", min, max);
+    ((v - min) / (max - min)).max(0.0).min(1.0)
+}
+///
+/// Thermometer code of a scalar in `[min, max]`.
+///
+/// `v` sets the first `round((v - min) / (max - min) * len)` bits, so that
+/// `distance(encode(a), encode(b))` is proportional to `|a - b|`.
+///
+#[derive(Clone, Copy, Debug)]
+pub struct ThermometerEncoder {
+    min: f64,
+    max: f64,
+}
+///
+///
+///
+impl ThermometerEncoder {
+    ///
+    ///
+    ///
+    pub fn new(min: f64, max: f64) -> Self {
+        assert!(max > min, "empty range [{}, {}]", min, max);
+        ThermometerEncoder { min, max }
+    }
+    ///
+    ///
+    ///
+    pub fn encode(&self, v: f64) -> BitTable {
+        let n = address_bits();
+        let level = (unit(v, self.min, self.max) * n as f64).round() as usize;
+        let bits: Vec<bool> = (0..n).map(|i| i < level).collect();
+        BitTable::from_slice(&bits)
+    }
+}
+///
+/// Random-flip level hypervectors of a scalar in `[min, max]`, quantized to `levels` levels.
+///
+/// Level `0` is a random hypervector; each following level flips `len / (2 * (levels - 1))`
+/// further bits, so that the first and last levels are at distance `len / 2`.
+///
+#[derive(Clone, Debug)]
+pub struct LevelEncoder {
+    min: f64,
+    max: f64,
+    levels: usize,
+    base: BitTable,
+    order: Vec<usize>,
+}
+///
+///
+///
+impl LevelEncoder {
+    ///
+    ///
+    ///
+    pub fn new(min: f64, max: f64, levels: usize, seed: u64) -> Self {
+        assert!(max > min, "empty range [{}, {}]", min, max);
+        assert!(levels >= 2, "at least two levels are needed");
+        let base = vsa::random(seed);
+        let order = permutation(base.len(), seed.wrapping_add(1));
+        LevelEncoder { min, max, levels, base, order }
+    }
+    ///
+    /// The level of `v`, in `0..levels`.
+    ///
+    pub fn level(&self, v: f64) -> usize {
+        (unit(v, self.min, self.max) * (self.levels - 1) as f64).round() as usize
+    }
+    ///
+    ///
+    ///
+    pub fn encode(&self, v: f64) -> BitTable {
+        let n = self.base.len();
+        let flips = self.level(v) * n / (2 * (self.levels - 1));
+        let mut bits: Vec<bool> = self.base.iter().collect();
+        for &i in self.order.iter().take(flips) { bits[i] = !bits[i]; }
+        BitTable::from_slice(&bits)
+    }
+}
+///
+/// Circular code of a periodic value, e.g. an angle or a time of day.
+///
+/// `v` is mapped to a shift `s = round(frac(v / period) * len)`, and bit `i` is set when
+/// `(i - s) mod len < len / 2`; the bits are then permuted and masked with seeded random
+/// vectors. `distance(encode(a), encode(b))` is twice the circular gap in shifts.
+///
+#[derive(Clone, Debug)]
+pub struct CyclicEncoder {
+    period: f64,
+    mask: BitTable,
+    order: Vec<usize>,
+}
+///
+///
+///
+impl CyclicEncoder {
+    ///
+    ///
+    ///
+    pub fn new(period: f64, seed: u64) -> Self {
+        assert!(period > 0.0, "period must be positive");
+        let mask = vsa::random(seed);
+        let order = permutation(mask.len(), seed.wrapping_add(1));
+        CyclicEncoder { period, mask, order }
+    }
+    ///
+    ///
+    ///
+    pub fn encode(&self, v: f64) -> BitTable {
+        let n = self.mask.len();
+        let mut phase = (v / self.period) % 1.0;
+        if phase < 0.0 { phase += 1.0; }
+        let s = ((phase * n as f64).round() as usize) % n;
+        let mut bits: Vec<bool> = vec![false; n];
+        for i in 0..n {
+            let on = (i + n - s) % n < n / 2;
+            bits[self.order[i]] = on ^ self.mask.get(self.order[i]);
+        }
+        BitTable::from_slice(&bits)
+    }
+}
+///
+/// Random projection with sign of vectors of dimension `dims`.
+///
+/// Bit `i` is set when `x` lies on the positive side of the `i`th random hyperplane,
+/// so that the expected distance is `len * angle(a, b) / pi`.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProjectionEncoder {
+    dims: usize,
+    planes: Vec<f64>,
+}
+///
+///
+///
+impl ProjectionEncoder {
+    ///
+    ///
+    ///
+    pub fn new(dims: usize, seed: u64) -> Self {
+        assert!(dims > 0, "dimension must be positive");
+        let mut rng = rng_from_seed(seed);
+        let planes: Vec<f64> = (0..address_bits()*dims).map(|_| rng.sample(StandardNormal)).collect();
+        ProjectionEncoder { dims, planes }
+    }
+    ///
+    /// An encoder with the given hyperplanes, `address_bits()` rows of `dims` values.
+    ///
+    pub(crate) fn from_planes(dims: usize, planes: Vec<f64>) -> Self {
+        assert!(dims > 0, "dimension must be positive");
+        assert_eq!(planes.len(), address_bits() * dims);
+        ProjectionEncoder { dims, planes }
+    }
+    ///
+    ///
+    ///
+    pub fn dims(&self) -> usize { self.dims }
+    ///
+    /// The hyperplanes, row by row.
+    ///
+    pub(crate) fn planes(&self) -> &[f64] { &self.planes }
+    ///
+    ///
+    ///
+    pub fn encode(&self, x: &[f64]) -> BitTable {
+        assert_eq!(x.len(), self.dims);
+        let bits: Vec<bool> = self.planes.chunks(self.dims)
+            .map(|w| w.iter().zip(x.iter()).map(|(a, b)| a * b).sum::<f64>() >= 0.0)
+            .collect();
+        BitTable::from_slice(&bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_scalar_thermometer() {
+        let e = ThermometerEncoder::new(0.0, 1.0);
+        let n = address_bits();
+        assert_eq!(e.encode(0.0).ones(), 0);
+        assert_eq!(e.encode(1.0).ones(), n);
+        assert_eq!(e.encode(2.0), e.encode(1.0));
+        assert_eq!(e.encode(0.25).distance(&e.encode(0.75)), n / 2);
+        let mut prev = 0;
+        for k in 1..11 {
+            let d = e.encode(0.0).distance(&e.encode(k as f64 / 10.0));
+            assert!(d >= prev);
+            prev = d;
+        }
+    }
+
+    #[test]
+    fn test_scalar_level() {
+        let e = LevelEncoder::new(-10.0, 10.0, 9, 1);
+        let n = address_bits();
+        assert_eq!(e.level(-10.0), 0);
+        assert_eq!(e.level(10.0), 8);
+        assert_eq!(e.encode(-10.0).distance(&e.encode(10.0)), n / 2);
+        let mut prev = 0;
+        for k in 0..9 {
+            let d = e.encode(-10.0).distance(&e.encode(-10.0 + 2.5 * k as f64));
+            assert!(d >= prev);
+            prev = d;
+        }
+        assert_eq!(e.encode(3.0), LevelEncoder::new(-10.0, 10.0, 9, 1).encode(3.0));
+    }
+
+    #[test]
+    fn test_scalar_cyclic() {
+        let e = CyclicEncoder::new(360.0, 2);
+        let n = address_bits();
+        assert_eq!(e.encode(0.0), e.encode(360.0));
+        assert_eq!(e.encode(-90.0), e.encode(270.0));
+        assert_eq!(e.encode(0.0).distance(&e.encode(180.0)), n);
+        // 337.5 and 22.5 degrees are as close as 0 and 45 degrees.
+        let d1 = e.encode(337.5).distance(&e.encode(22.5));
+        let d2 = e.encode(0.0).distance(&e.encode(45.0));
+        assert_eq!(d1, d2);
+        assert_eq!(d1, n / 4);
+        // Addresses are spread, not a plain half-on block.
+        let ones = e.encode(0.0).ones();
+        assert!(ones > n / 4 && ones < 3 * n / 4);
+    }
+
+    #[test]
+    fn test_scalar_projection() {
+        let e = ProjectionEncoder::new(3, 3);
+        let n = address_bits();
+        let x = [1.0, 2.0, 3.0];
+        let near = [1.05, 2.0, 2.95];
+        let far = [-1.0, 0.5, -3.0];
+        let neg = [-1.0, -2.0, -3.0];
+        let dn = e.encode(&x).distance(&e.encode(&near));
+        let df = e.encode(&x).distance(&e.encode(&far));
+        assert!(dn < df, "near {} far {}", dn, df);
+        assert!(dn < n / 10);
+        assert_eq!(e.encode(&x).distance(&e.encode(&neg)), n);
+        assert_eq!(e.encode(&x), e.encode(&[2.0, 4.0, 6.0]));
+    }
+}
@@ -0,0 +1,187 @@
+//
+// Locality-sensitive hashing of dense `f32` embeddings (SimHash, Charikar 2002).
+//
+// Bit `i` of the address is the sign of the dot product of the embedding with the `i`th
+// row of a seeded Gaussian projection matrix, as computed by `scalar::ProjectionEncoder`.
+// The matrix is saved with the memory, in the same header and little-endian layout as
+// the `interop` files:
+//
+//     SDM PROJECTION
+//     SDM-Version: v0.0.1
+//     Format: binary
+//     Order-of-bytes: little-endian
+//     Projection-Version: <version>  (of the matrix generation)
+//     Bits: <bits>                   (rows)
+//     Dims: <dims>                   (columns, at most `MAX_DIMS`)
+//     Seed: <seed>
+//
+//     <bits * dims IEEE 754 double-precision floats, row by row>
+//
+// Loading uses the stored matrix as is, so an embedding keeps its address even if the
+// generation of the matrix changes in a later version.
+//
+///
+use std::io::{self, BufRead, Write};
+///
+use bittable::BitTable;
+///
+use interop::{invalid, parse_field, read_header, read_u64, write_u64, expect_field, SDM_VERSION};
+///
+use super::address_bits;
+///
+use super::scalar::ProjectionEncoder;
+///
+/// Version of the generation of the projection matrix from the seed.
+///
+pub const PROJECTION_VERSION: u32 = 1;
+///
+/// Largest embedding dimension accepted when reading a projection.
+///
+pub const MAX_DIMS: usize = 1 << 16;
+///
+/// Random-hyperplane encoder of `f32` embeddings of dimension `dims`.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimHashEncoder {
+    version: u32,
+    seed: u64,
+    projection: ProjectionEncoder,
+}
+///
+///
+///
+impl SimHashEncoder {
+    ///
+    /// An encoder with a projection matrix drawn from `seed`.
+    ///
+    pub fn new(dims: usize, seed: u64) -> Self {
+        SimHashEncoder { version: PROJECTION_VERSION, seed, projection: ProjectionEncoder::new(dims, seed) }
+    }
+    ///
+    ///
+    ///
+    pub fn dims(&self) -> usize { self.projection.dims() }
+    ///
+    ///
+    ///
+    pub fn seed(&self) -> u64 { self.seed }
+    ///
+    /// Version of the generation of the projection matrix.
+    ///
+    pub fn version(&self) -> u32 { self.version }
+    ///
+    /// The address of `x`.
+    ///
+    pub fn encode(&self, x: &[f32]) -> BitTable {
+        assert_eq!(x.len(), self.dims(), "embedding of dimension {}, expected {}", x.len(), self.dims());
+        let x: Vec<f64> = x.iter().map(|&v| f64::from(v)).collect();
+        self.projection.encode(&x)
+    }
+    ///
+    /// Write the projection matrix.
+    ///
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        write!(w, "SDM PROJECTION\n")?;
+        write!(w, "SDM-Version: {}\n", SDM_VERSION)?;
+        write!(w, "Format: binary\n")?;
+        write!(w, "Order-of-bytes: little-endian\n")?;
+        write!(w, "Projection-Version: {}\n", self.version)?;
+        write!(w, "Bits: {}\n", address_bits())?;
+        write!(w, "Dims: {}\n", self.dims())?;
+        write!(w, "Seed: {}\n", self.seed)?;
+        write!(w, "\n")?;
+        for &x in self.projection.planes() { write_u64(w, x.to_bits())?; }
+        Ok(())
+    }
+    ///
+    /// Read a projection matrix written by `write`.
+    ///
+    pub fn read<R: BufRead>(r: &mut R) -> io::Result<Self> {
+        let header = read_header(r, "SDM PROJECTION")?;
+        let version = parse_field(&header, "Projection-Version")?;
+        if version == 0 || version > u64::from(PROJECTION_VERSION) {
+            return Err(invalid(format!("unsupported projection version {}", version)));
+        }
+        expect_field(&header, "Bits", address_bits())?;
+        let dims = parse_field(&header, "Dims")?;
+        if dims == 0 || dims > MAX_DIMS as u64 {
+            return Err(invalid(format!("projection of dimension {}, expected 1 to {}", dims, MAX_DIMS)));
+        }
+        let dims = dims as usize;
+        let len = address_bits().checked_mul(dims)
+            .ok_or_else(|| invalid(format!("projection of dimension {} too large", dims)))?;
+        let seed = parse_field(&header, "Seed")?;
+        let mut planes: Vec<f64> = Vec::with_capacity(len);
+        for _ in 0..len { planes.push(f64::from_bits(read_u64(r)?)); }
+        Ok(SimHashEncoder { version: version as u32, seed, projection: ProjectionEncoder::from_planes(dims, planes) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::{Cursor, ErrorKind};
+    use super::*;
+
+    #[test]
+    fn test_simhash_seeded() {
+        let x = [0.5f32, -1.25, 3.0, 0.0];
+        assert_eq!(SimHashEncoder::new(4, 1).encode(&x), SimHashEncoder::new(4, 1).encode(&x));
+        assert!(SimHashEncoder::new(4, 1).encode(&x) != SimHashEncoder::new(4, 2).encode(&x));
+    }
+
+    #[test]
+    fn test_simhash_angle() {
+        let e = SimHashEncoder::new(8, 3);
+        let n = address_bits();
+        let x = [1.0f32, 2.0, 3.0, 4.0, -1.0, -2.0, 0.5, 0.0];
+        let near: Vec<f32> = x.iter().map(|&v| v * 1.02 + 0.01).collect();
+        let neg: Vec<f32> = x.iter().map(|&v| -v).collect();
+        let far = [-3.0f32, 1.0, -0.5, 2.0, 4.0, -1.0, 0.0, 1.0];
+        let dn = e.encode(&x).distance(&e.encode(&near));
+        let df = e.encode(&x).distance(&e.encode(&far));
+        assert!(dn < n / 10, "near at distance {}", dn);
+        assert!(dn < df, "near {} far {}", dn, df);
+        assert_eq!(e.encode(&x).distance(&e.encode(&neg)), n);
+    }
+
+    #[test]
+    fn test_simhash_round_trip() {
+        let e = SimHashEncoder::new(5, 4);
+        let mut buf: Vec<u8> = Vec::new();
+        e.write(&mut buf).unwrap();
+        let f = SimHashEncoder::read(&mut Cursor::new(buf)).unwrap();
+        assert_eq!(e, f);
+        assert_eq!(f.version(), PROJECTION_VERSION);
+        let x = [0.1f32, 0.2, -0.3, 0.4, -0.5];
+        assert_eq!(e.encode(&x), f.encode(&x));
+    }
+
+    #[test]
+    fn test_simhash_read_errors() {
+        let mut buf: Vec<u8> = Vec::new();
+        SimHashEncoder::new(2, 5).write(&mut buf).unwrap();
+        let end = buf.windows(2).position(|w| w == b"\n\n").unwrap();
+        let header = String::from_utf8(buf[..end].to_vec()).unwrap();
+        let mut future = header.replace("Projection-Version: 1", "Projection-Version: 9").into_bytes();
+        future.extend_from_slice(&buf[end..]);
+        let e = SimHashEncoder::read(&mut Cursor::new(future)).err().unwrap();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+        buf.pop();
+        let e = SimHashEncoder::read(&mut Cursor::new(buf)).err().unwrap();
+        assert_eq!(e.kind(), ErrorKind::UnexpectedEof);
+        for dims in ["0", "65537", "18446744073709551615"].iter() {
+            let mut huge = header.replace("Dims: 2", &format!("Dims: {}", dims)).into_bytes();
+            huge.extend_from_slice(b"\n\n");
+            let e = SimHashEncoder::read(&mut Cursor::new(huge)).err().unwrap();
+            assert_eq!(e.kind(), ErrorKind::InvalidData, "Dims: {}", dims);
+        }
+    }
+
+    #[test]
+    fn test_simhash_projection() {
+        let x = [0.5f32, -1.25, 3.0];
+        let y: Vec<f64> = x.iter().map(|&v| f64::from(v)).collect();
+        assert_eq!(SimHashEncoder::new(3, 6).encode(&x), ProjectionEncoder::new(3, 6).encode(&y));
+    }
+}
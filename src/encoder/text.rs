@@ -0,0 +1,113 @@
+//
+// Text encoders.
+//
+// Each character gets a random item vector. An n-gram `c_1 c_2 ... c_n` is encoded as
+// `permute(c_1, n-1) ^ permute(c_2, n-2) ^ ... ^ c_n`, so that the same characters in
+// another order give an unrelated vector. A document is the bundle of its n-grams:
+// strings sharing most of their n-grams get close addresses.
+//
+///
+use bittable::BitTable;
+///
+use item_memory::ItemMemory;
+///
+use vsa;
+///
+/// Encoder of strings into addresses by bundled n-grams.
+///
+#[derive(Clone, Debug)]
+pub struct TextEncoder {
+    items: ItemMemory,
+    n: usize,
+    seed: u64,
+}
+///
+///
+///
+impl TextEncoder {
+    ///
+    /// An encoder of `n`-grams; `seed` seeds the character vectors and the bundling ties.
+    ///
+    pub fn new(n: usize, seed: u64) -> Self {
+        assert!(n > 0, "n-grams need at least one character");
+        TextEncoder { items: ItemMemory::new(seed), n, seed }
+    }
+    ///
+    /// The item vector of character `c`.
+    ///
+    pub fn char_vector(&mut self, c: char) -> BitTable {
+        self.items.get(&c.to_string()).clone()
+    }
+    ///
+    /// The hypervector of one n-gram, of any length.
+    ///
+    pub fn ngram(&mut self, gram: &[char]) -> BitTable {
+        assert!(!gram.is_empty(), "empty n-gram");
+        let k = gram.len();
+        let mut hv = self.char_vector(gram[k-1]);
+        for (j, &c) in gram[..k-1].iter().enumerate() {
+            let v = self.char_vector(c);
+            hv = vsa::bind(&hv, &vsa::permute(&v, k-1-j));
+        }
+        hv
+    }
+    ///
+    /// The document vector of `text`: the bundle of its n-grams.
+    ///
+    /// A text shorter than `n` is encoded as a single gram.
+    ///
+    pub fn encode(&mut self, text: &str) -> BitTable {
+        let chars: Vec<char> = text.chars().collect();
+        assert!(!chars.is_empty(), "cannot encode an empty text");
+        if chars.len() <= self.n {
+            return self.ngram(&chars);
+        }
+        let n = self.n;
+        let grams: Vec<BitTable> = chars.windows(n).map(|w| self.ngram(w)).collect();
+        vsa::bundle(&grams, self.seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::TextEncoder;
+
+    #[test]
+    fn test_text_ngram_order() {
+        let mut e = TextEncoder::new(3, 1);
+        let abc = e.ngram(&['a', 'b', 'c']);
+        let cba = e.ngram(&['c', 'b', 'a']);
+        assert_eq!(abc, e.ngram(&['a', 'b', 'c']));
+        let d = abc.distance(&cba);
+        assert!(d > abc.len() / 4, "reversed n-gram at distance {}", d);
+    }
+
+    #[test]
+    fn test_text_seeded() {
+        let a = TextEncoder::new(3, 7).encode("sparse distributed memory");
+        let b = TextEncoder::new(3, 7).encode("sparse distributed memory");
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_text_short() {
+        let mut e = TextEncoder::new(3, 2);
+        let hv = e.encode("ab");
+        assert_eq!(hv, e.ngram(&['a', 'b']));
+    }
+
+    #[test]
+    fn test_text_similarity() {
+        let radius = 54;
+        let mut e = TextEncoder::new(3, 3);
+        let a = e.encode("the quick brown fox jumps over the lazy dog");
+        let b = e.encode("the quick brown fox jumps over the lazy cat");
+        let c = e.encode("sparse distributed memory stores patterns");
+        let near = a.distance(&b);
+        let far = a.distance(&c);
+        assert!(near <= radius, "similar strings at distance {}", near);
+        assert!(near < far, "near {} far {}", near, far);
+        assert!(far > a.len() / 3, "unrelated strings at distance {}", far);
+    }
+}
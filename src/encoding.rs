@@ -0,0 +1,221 @@
+//
+// Hex and Base64 text encoding of `BitTable` addresses.
+//
+// The text is produced by the C library (`bs_to_hex`, `bs_to_b64`) through `Bitstring`,
+// so that it can be exchanged with the existing C tooling: bit `i` of a `BitTable`
+// is bit `i` of the C bit string, stored most significant bit first in 64-bit words.
+//
+// `to_bytes` and `from_bytes` pack the bits the same way into bytes: bit `i` is bit
+// `7 - i % 8` of byte `i / 8`, the layout of the C API and of NumPy's `packbits`.
+//
+///
+use std::error::Error;
+///
+use std::fmt;
+///
+use bittable::BitTable;
+///
+use bitstring::Bitstring;
+///
+/// Error raised when decoding an address from text.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EncodingError {
+    ///
+    /// The text or bytes do not have the length expected for the address dimension.
+    ///
+    Length {
+        ///
+        expected: usize,
+        ///
+        found: usize,
+    },
+    ///
+    /// The text contains characters outside of its alphabet.
+    ///
+    Invalid,
+}
+///
+///
+///
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EncodingError::Length { expected, found } =>
+                write!(f, "expected {} characters or bytes, found {}", expected, found),
+            EncodingError::Invalid => write!(f, "invalid character"),
+        }
+    }
+}
+///
+///
+///
+impl Error for EncodingError {
+    fn description(&self) -> &str { "invalid address encoding" }
+}
+///
+/// Copy a `BitTable` into a C bit string of the same length.
+///
+pub fn to_bitstring(bt: &BitTable) -> Bitstring {
+    let mut bs = Bitstring::zeros(bt.len());
+    for i in 0..bt.len() { if bt.get(i) { bs.set(i, true); } }
+    bs
+}
+///
+/// Copy a C bit string into a `BitTable`.
+///
+pub fn from_bitstring(bs: &Bitstring) -> BitTable {
+    let bits: Vec<bool> = (0..bs.len()).map(|i| bs.get(i)).collect();
+    BitTable::from_slice(&bits)
+}
+///
+/// Number of bits of a `BitTable` address.
+///
+fn address_bits() -> usize { BitTable::new().len() }
+///
+/// Number of bytes of a packed address.
+///
+pub fn address_bytes() -> usize { (address_bits() + 7) / 8 }
+///
+/// Pack the bits of an address into bytes, most significant bit first.
+///
+pub fn to_bytes(bt: &BitTable) -> Vec<u8> {
+    let mut bytes = vec![0u8; (bt.len() + 7) / 8];
+    for i in 0..bt.len() {
+        if bt.get(i) { bytes[i / 8] |= 0x80 >> (i % 8); }
+    }
+    bytes
+}
+///
+/// Unpack the output of `to_bytes`.
+///
+pub fn from_bytes(bytes: &[u8]) -> Result<BitTable, EncodingError> {
+    if bytes.len() != address_bytes() {
+        return Err(EncodingError::Length { expected: address_bytes(), found: bytes.len() });
+    }
+    let bits: Vec<bool> = (0..address_bits()).map(|i| bytes[i / 8] & (0x80 >> (i % 8)) != 0).collect();
+    Ok(BitTable::from_slice(&bits))
+}
+///
+/// Text encoding of an address.
+///
+pub trait Encoding: Sized {
+    ///
+    /// Hexadecimal text, 16 digits per 64-bit word.
+    ///
+    fn to_hex(&self) -> String;
+    ///
+    /// Parse the output of `to_hex`.
+    ///
+    fn from_hex(s: &str) -> Result<Self, EncodingError>;
+    ///
+    /// Base64 text of the 64-bit words, in native byte order.
+    ///
+    fn to_base64(&self) -> String;
+    ///
+    /// Parse the output of `to_base64`.
+    ///
+    fn from_base64(s: &str) -> Result<Self, EncodingError>;
+}
+///
+///
+///
+impl Encoding for BitTable {
+    fn to_hex(&self) -> String {
+        to_bitstring(self).to_hex()
+    }
+
+    fn from_hex(s: &str) -> Result<Self, EncodingError> {
+        let n = address_bits();
+        let expected = (n + 63) / 64 * 16;
+        if s.len() != expected {
+            return Err(EncodingError::Length { expected, found: s.len() });
+        }
+        Bitstring::from_hex(n, s).map(|bs| from_bitstring(&bs)).ok_or(EncodingError::Invalid)
+    }
+
+    fn to_base64(&self) -> String {
+        to_bitstring(self).to_b64()
+    }
+
+    fn from_base64(s: &str) -> Result<Self, EncodingError> {
+        let n = address_bits();
+        let expected = ((n + 63) / 64 * 8 + 2) / 3 * 4;
+        if s.len() != expected {
+            return Err(EncodingError::Length { expected, found: s.len() });
+        }
+        let alphabet = |c: char| c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '=';
+        if !s.chars().all(alphabet) {
+            return Err(EncodingError::Invalid);
+        }
+        Bitstring::from_b64(n, s).map(|bs| from_bitstring(&bs)).ok_or(EncodingError::Invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Encoding, EncodingError};
+    use super::{to_bitstring, from_bitstring, to_bytes, from_bytes, address_bytes};
+    use super::BitTable;
+
+    #[test]
+    fn test_encoding_bitstring_round_trip() {
+        let bt = BitTable::new_rand();
+        let bs = to_bitstring(&bt);
+        for i in 0..bt.len() { assert_eq!(bt.get(i), bs.get(i)); }
+        assert_eq!(from_bitstring(&bs), bt);
+    }
+
+    #[test]
+    fn test_encoding_hex_layout() {
+        // Bit 0 is the most significant bit of the first word, as in `bs_get_bit`.
+        let mut bt = BitTable::new();
+        bt.set(0);
+        let hex = bt.to_hex();
+        assert!(hex.starts_with("8000000000000000"));
+        assert!(hex[1..].chars().all(|c| c == '0'));
+    }
+
+    #[test]
+    fn test_encoding_bytes() {
+        let bt = BitTable::new_rand();
+        assert_eq!(to_bytes(&bt).len(), address_bytes());
+        assert_eq!(from_bytes(&to_bytes(&bt)), Ok(bt));
+        let mut one = BitTable::new();
+        one.set(0);
+        assert_eq!(to_bytes(&one)[0], 0x80);
+        assert_eq!(from_bytes(&[0u8; 3]), Err(EncodingError::Length { expected: address_bytes(), found: 3 }));
+    }
+
+    #[test]
+    fn test_encoding_hex_round_trip() {
+        let bt = BitTable::new_rand();
+        assert_eq!(BitTable::from_hex(&bt.to_hex()), Ok(bt));
+    }
+
+    #[test]
+    fn test_encoding_base64_round_trip() {
+        let bt = BitTable::new_rand();
+        assert_eq!(BitTable::from_base64(&bt.to_base64()), Ok(bt));
+    }
+
+    #[test]
+    fn test_encoding_length() {
+        let hex = BitTable::new_rand().to_hex();
+        match BitTable::from_hex(&hex[2..]) {
+            Err(EncodingError::Length { expected, found }) => assert_eq!(expected, found + 2),
+            other => panic!("unexpected {:?}", other),
+        }
+        let b64 = BitTable::new_rand().to_base64();
+        assert!(BitTable::from_base64(&format!("{}AAAA", b64)).is_err());
+    }
+
+    #[test]
+    fn test_encoding_invalid() {
+        let hex: String = BitTable::new_rand().to_hex().chars().map(|_| 'z').collect();
+        assert_eq!(BitTable::from_hex(&hex), Err(EncodingError::Invalid));
+        let b64: String = BitTable::new_rand().to_base64().chars().map(|_| '*').collect();
+        assert_eq!(BitTable::from_base64(&b64), Err(EncodingError::Invalid));
+    }
+}
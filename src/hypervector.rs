@@ -0,0 +1,151 @@
+//
+// Bit-vectors usable as addresses and data of an SDM.
+//
+// The crate has several representations of a bit-vector: `bittable::BitTable`, the
+// `vector::Vector` wrapper around it, the `array::BoolArray` array of `bool` and the C
+// `bitstring::Bitstring`. `HyperVector` is what they have in common, and what `SDM` and
+// `AddressSpace` need from their addresses.
+//
+// `BitTable`, `Vector` and `BoolArray` have a length fixed at compilation; their
+// `random` and `from_bools` panic when asked for another length. A `Bitstring` has any
+// length; `SDM::with_address_space` gives a memory one counter per bit of its addresses.
+//
+///
+use std::fmt;
+///
+use bittable::BitTable;
+///
+/// A vector of bits with the operations of hyperdimensional computing.
+///
+pub trait HyperVector: Clone + PartialEq + fmt::Debug {
+    ///
+    /// A uniformly random vector of `bits` bits.
+    ///
+    fn random(bits: usize) -> Self;
+    ///
+    /// The vector with bit `i` set to `bits[i]`.
+    ///
+    fn from_bools(bits: &[bool]) -> Self;
+    ///
+    /// Number of bits.
+    ///
+    fn len(&self) -> usize;
+    ///
+    /// Whether the vector has no bits.
+    ///
+    fn is_empty(&self) -> bool { self.len() == 0 }
+    ///
+    /// Bit `i`.
+    ///
+    fn get(&self, i: usize) -> bool;
+    ///
+    /// Set bit `i` to `value`.
+    ///
+    fn set(&mut self, i: usize, value: bool);
+    ///
+    /// Hamming distance to `other`, of the same length.
+    ///
+    fn distance(&self, other: &Self) -> usize;
+    ///
+    ///
+    ///
+    fn xor(&self, other: &Self) -> Self;
+    ///
+    ///
+    ///
+    fn and(&self, other: &Self) -> Self;
+    ///
+    ///
+    ///
+    fn or(&self, other: &Self) -> Self;
+    ///
+    /// Number of bits set to `1`.
+    ///
+    fn popcount(&self) -> usize;
+    ///
+    /// The bits, in order.
+    ///
+    fn to_bools(&self) -> Vec<bool> {
+        (0..self.len()).map(|i| self.get(i)).collect()
+    }
+}
+///
+/// Copy of `v` in another representation.
+///
+pub fn convert<A: HyperVector, B: HyperVector>(v: &A) -> B {
+    B::from_bools(&v.to_bools())
+}
+///
+/// Number of bits of a `BitTable`.
+///
+fn table_bits() -> usize { BitTable::new().len() }
+///
+///
+///
+impl HyperVector for BitTable {
+    fn random(bits: usize) -> Self {
+        assert_eq!(bits, table_bits(), "a BitTable has {} bits", table_bits());
+        BitTable::new_rand()
+    }
+    fn from_bools(bits: &[bool]) -> Self {
+        assert_eq!(bits.len(), table_bits(), "a BitTable has {} bits", table_bits());
+        BitTable::from_slice(bits)
+    }
+    fn len(&self) -> usize { BitTable::len(self) }
+    fn get(&self, i: usize) -> bool { BitTable::get(self, i) }
+    fn set(&mut self, i: usize, value: bool) {
+        if value {
+            BitTable::set(self, i);
+        } else if BitTable::get(self, i) {
+            // `BitTable` only sets bits: clear it by flipping it with a one-hot table.
+            let mut bit = BitTable::new();
+            BitTable::set(&mut bit, i);
+            *self = self.clone() ^ bit;
+        }
+    }
+    fn distance(&self, other: &Self) -> usize { BitTable::distance(self, other) }
+    fn xor(&self, other: &Self) -> Self { self.clone() ^ other.clone() }
+    fn and(&self, other: &Self) -> Self { self.clone() & other.clone() }
+    fn or(&self, other: &Self) -> Self { self.clone() | other.clone() }
+    fn popcount(&self) -> usize { BitTable::distance(self, &BitTable::new()) }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+
+    use super::*;
+
+    /// Check the operations of `V` on vectors of `bits` bits.
+    pub(crate) fn check<V: HyperVector>(bits: usize) {
+        let a = V::random(bits);
+        let b = V::random(bits);
+        assert_eq!(a.len(), bits);
+        assert_eq!(V::from_bools(&a.to_bools()), a);
+        assert_eq!(a.distance(&b), a.xor(&b).popcount());
+        assert_eq!(a.distance(&a), 0);
+        assert_eq!(a.and(&b).popcount() + a.or(&b).popcount(), a.popcount() + b.popcount());
+        assert_eq!(a.xor(&b).xor(&b), a);
+        let mut c = a.clone();
+        let flipped = !c.get(bits - 1);
+        c.set(bits - 1, flipped);
+        assert_eq!(c.get(bits - 1), flipped);
+        assert_eq!(c.distance(&a), 1);
+        c.set(bits - 1, !flipped);
+        assert_eq!(c, a);
+        let zeros = V::from_bools(&vec![false; bits]);
+        assert_eq!(zeros.popcount(), 0);
+        assert_eq!(V::from_bools(&vec![true; bits]).popcount(), bits);
+    }
+
+    #[test]
+    fn test_hypervector_bittable() {
+        check::<BitTable>(table_bits());
+    }
+
+    #[test]
+    fn test_hypervector_convert() {
+        let a = BitTable::new_rand();
+        let b: BitTable = convert(&a);
+        assert_eq!(a, b);
+    }
+}
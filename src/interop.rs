@@ -0,0 +1,387 @@
+//
+// Import and export of the on-disk layouts of the original C `sdm` library.
+//
+// Both files start with a text header of `Key: value` lines, closed by an empty line,
+// followed by little-endian binary data.
+//
+// Address space (`as_save`):
+//
+//     SDM ADDRESS SPACE
+//     SDM-Version: v0.0.1
+//     Format: binary
+//     Order-of-bytes: little-endian
+//     Bits-per-Bitstring: <bits>
+//     Bitstring-Length: <len>        (64-bit words per address)
+//     Sample: <sample>               (number of hard locations)
+//
+//     <sample * len words of 64 bits, most significant bit first, as `bs_get_bit`>
+//
+// Counters (`counter_save`):
+//
+//     SDM COUNTER
+//     SDM-Version: v0.0.1
+//     Format: binary
+//     Order-of-bytes: little-endian
+//     Bits: <bits>
+//     Sample: <sample>
+//
+//     <sample * bits signed 32-bit counters, hard location by hard location>
+//
+// The Rust counters are `i16`: imported values saturate to `i16::MIN..i16::MAX`. Counter
+// files written by earlier versions of this crate carry a `Counter-Size: 4` line, which
+// the C library does not write; it is accepted on import and no longer exported.
+//
+// The word layout is checked against the C `bitstring` functions; the headers follow
+// `as_save` and `counter_save` but are not checked against files of the C tools.
+//
+#[allow(missing_docs)]
+include!(concat!(env!("OUT_DIR"), "/dimensions.rs"));
+///
+use std::collections::HashMap;
+///
+use std::fs::File;
+///
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+///
+use std::path::Path;
+///
+use bittable::BitTable;
+///
+use address_space::AddressSpace;
+///
+use counter::Counter;
+///
+use SDM;
+///
+/// Version written in the headers.
+///
+pub(crate) const SDM_VERSION: &str = "v0.0.1";
+///
+/// Number of bits in a C `bitstring_t` word.
+///
+const WORD_BITS: usize = 64;
+///
+///
+///
+pub(crate) fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+///
+/// Read the text header: the magic line, then `Key: value` lines up to an empty line.
+///
+pub(crate) fn read_header<R: BufRead>(r: &mut R, magic: &str) -> io::Result<HashMap<String, String>> {
+    let mut line = String::new();
+    r.read_line(&mut line)?;
+    if line.trim_end() != magic {
+        return Err(invalid(format!("expected `{}`, found `{}`", magic, line.trim_end())));
+    }
+    let mut header: HashMap<String, String> = HashMap::new();
+    loop {
+        line.clear();
+        if r.read_line(&mut line)? == 0 {
+            return Err(invalid("unterminated header".to_string()));
+        }
+        let l = line.trim_end();
+        if l.is_empty() { break; }
+        let mut kv = l.splitn(2, ':');
+        let key = kv.next().unwrap_or("").trim().to_string();
+        let value = kv.next().ok_or_else(|| invalid(format!("malformed header line `{}`", l)))?;
+        header.insert(key, value.trim().to_string());
+    }
+    match header.get("Format").map(|s| s.as_str()) {
+        Some("binary") => {},
+        other => return Err(invalid(format!("unsupported format {:?}", other))),
+    }
+    match header.get("Order-of-bytes").map(|s| s.as_str()) {
+        Some("little-endian") => {},
+        other => return Err(invalid(format!("unsupported byte order {:?}", other))),
+    }
+    Ok(header)
+}
+///
+/// Get a header value as a number.
+///
+pub(crate) fn parse_field(header: &HashMap<String, String>, key: &str) -> io::Result<u64> {
+    header.get(key)
+        .ok_or_else(|| invalid(format!("missing header field `{}`", key)))?
+        .parse()
+        .map_err(|_| invalid(format!("header field `{}` is not a number", key)))
+}
+///
+/// Get a header value as a number, checking it against the expected value.
+///
+pub(crate) fn expect_field(header: &HashMap<String, String>, key: &str, expected: usize) -> io::Result<()> {
+    let value = parse_field(header, key)?;
+    if value != expected as u64 {
+        return Err(invalid(format!("`{}` is {}, this build expects {}", key, value, expected)));
+    }
+    Ok(())
+}
+///
+/// Pack an address into C words: bit `i` is bit `63 - i % 64` of word `i / 64`.
+///
+pub(crate) fn to_words(bt: &BitTable) -> Vec<u64> {
+    let mut words: Vec<u64> = vec![0; (bt.len() + WORD_BITS - 1) / WORD_BITS];
+    for i in 0..bt.len() {
+        if bt.get(i) { words[i / WORD_BITS] |= 1u64 << (WORD_BITS - 1 - i % WORD_BITS); }
+    }
+    words
+}
+///
+/// Inverse of `to_words`.
+///
+pub(crate) fn from_words(words: &[u64], bits: usize) -> BitTable {
+    let v: Vec<bool> = (0..bits)
+        .map(|i| (words[i / WORD_BITS] >> (WORD_BITS - 1 - i % WORD_BITS)) & 1 == 1)
+        .collect();
+    BitTable::from_slice(&v)
+}
+///
+///
+///
+pub(crate) fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut b = [0u8; 8];
+    r.read_exact(&mut b)?;
+    Ok(b.iter().rev().fold(0u64, |acc, &x| (acc << 8) | u64::from(x)))
+}
+///
+///
+///
+pub(crate) fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut b = [0u8; 4];
+    r.read_exact(&mut b)?;
+    Ok(b.iter().rev().fold(0u32, |acc, &x| (acc << 8) | u32::from(x)))
+}
+///
+///
+///
+pub(crate) fn read_i32<R: Read>(r: &mut R) -> io::Result<i32> {
+    read_u32(r).map(|x| x as i32)
+}
+///
+///
+///
+pub(crate) fn write_u64<W: Write>(w: &mut W, x: u64) -> io::Result<()> {
+    let mut b = [0u8; 8];
+    for (k, byte) in b.iter_mut().enumerate() { *byte = (x >> (8 * k)) as u8; }
+    w.write_all(&b)
+}
+///
+///
+///
+pub(crate) fn write_u32<W: Write>(w: &mut W, x: u32) -> io::Result<()> {
+    let mut b = [0u8; 4];
+    for (k, byte) in b.iter_mut().enumerate() { *byte = (x >> (8 * k)) as u8; }
+    w.write_all(&b)
+}
+///
+///
+///
+pub(crate) fn write_i32<W: Write>(w: &mut W, x: i32) -> io::Result<()> {
+    write_u32(w, x as u32)
+}
+///
+/// Read an address space in the C layout.
+///
+pub fn read_address_space<R: BufRead>(r: &mut R) -> io::Result<AddressSpace> {
+    let header = read_header(r, "SDM ADDRESS SPACE")?;
+    let bits = BitTable::new().len();
+    let len = (bits + WORD_BITS - 1) / WORD_BITS;
+    expect_field(&header, "Bits-per-Bitstring", bits)?;
+    expect_field(&header, "Bitstring-Length", len)?;
+    expect_field(&header, "Sample", ADD_DIM)?;
+    let mut vec: Vec<BitTable> = Vec::with_capacity(ADD_DIM);
+    let mut words: Vec<u64> = vec![0; len];
+    for _ in 0..ADD_DIM {
+        for w in words.iter_mut() { *w = read_u64(r)?; }
+        vec.push(from_words(&words, bits));
+    }
+    Ok(AddressSpace::from_vec(vec))
+}
+///
+/// Write an address space in the C layout.
+///
+pub fn write_address_space<W: Write>(a: &AddressSpace, w: &mut W) -> io::Result<()> {
+    let bits = BitTable::new().len();
+    let len = (bits + WORD_BITS - 1) / WORD_BITS;
+    write!(w, "SDM ADDRESS SPACE\n")?;
+    write!(w, "SDM-Version: {}\n", SDM_VERSION)?;
+    write!(w, "Format: binary\n")?;
+    write!(w, "Order-of-bytes: little-endian\n")?;
+    write!(w, "Bits-per-Bitstring: {}\n", bits)?;
+    write!(w, "Bitstring-Length: {}\n", len)?;
+    write!(w, "Sample: {}\n", a.len())?;
+    write!(w, "\n")?;
+    for i in 0..a.len() {
+        for word in to_words(a.get(i)) { write_u64(w, word)?; }
+    }
+    Ok(())
+}
+///
+/// Read counters in the C layout, saturating them to `i16`.
+///
+pub fn read_counter<R: BufRead>(r: &mut R) -> io::Result<Counter> {
+    let header = read_header(r, "SDM COUNTER")?;
+    let bits = BitTable::new().len();
+    expect_field(&header, "Bits", bits)?;
+    expect_field(&header, "Sample", ADD_DIM)?;
+    if header.contains_key("Counter-Size") { expect_field(&header, "Counter-Size", 4)?; }
+    let mut v: Vec<i16> = Vec::with_capacity(bits * ADD_DIM);
+    for _ in 0..(bits * ADD_DIM) {
+        let x = read_i32(r)?;
+        v.push(x.max(i32::from(i16::min_value())).min(i32::from(i16::max_value())) as i16);
+    }
+    Ok(Counter::from_vec(v))
+}
+///
+/// Write counters in the C layout.
+///
+pub fn write_counter<W: Write>(c: &Counter, w: &mut W) -> io::Result<()> {
+    let bits = BitTable::new().len();
+    write!(w, "SDM COUNTER\n")?;
+    write!(w, "SDM-Version: {}\n", SDM_VERSION)?;
+    write!(w, "Format: binary\n")?;
+    write!(w, "Order-of-bytes: little-endian\n")?;
+    write!(w, "Bits: {}\n", bits)?;
+    write!(w, "Sample: {}\n", c.len() / bits)?;
+    write!(w, "\n")?;
+    for &x in c.as_slice() { write_i32(w, i32::from(x))?; }
+    Ok(())
+}
+///
+/// Load an SDM from the address space and counter files written by the C tools.
+///
+pub fn load_sdm<P: AsRef<Path>, Q: AsRef<Path>>(as_path: P, counter_path: Q) -> io::Result<SDM> {
+    let a = read_address_space(&mut BufReader::new(File::open(as_path)?))?;
+    let c = read_counter(&mut BufReader::new(File::open(counter_path)?))?;
+    Ok(SDM::from_parts(a, c))
+}
+///
+/// Save an SDM as an address space file and a counter file readable by the C tools.
+///
+pub fn save_sdm<P: AsRef<Path>, Q: AsRef<Path>>(sdm: &SDM, as_path: P, counter_path: Q) -> io::Result<()> {
+    let mut wa = BufWriter::new(File::create(as_path)?);
+    write_address_space(sdm.address_space(), &mut wa)?;
+    wa.flush()?;
+    let mut wc = BufWriter::new(File::create(counter_path)?);
+    write_counter(sdm.counter(), &mut wc)?;
+    wc.flush()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::{Cursor, ErrorKind};
+    use super::*;
+    use bitstring::Bitstring;
+
+    /// Hand-built address space file: location `i` has words `[i, !0, 0, ...]`.
+    fn address_space_fixture(bits: usize, len: usize, sample: usize) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        v.extend_from_slice(b"SDM ADDRESS SPACE\nSDM-Version: v0.0.1\nFormat: binary\n");
+        v.extend_from_slice(b"Order-of-bytes: little-endian\n");
+        v.extend_from_slice(format!("Bits-per-Bitstring: {}\nBitstring-Length: {}\nSample: {}\n\n", bits, len, sample).as_bytes());
+        for i in 0..sample {
+            for k in 0..len {
+                let word: u64 = match k { 0 => i as u64, 1 => !0, _ => 0 };
+                write_u64(&mut v, word).unwrap();
+            }
+        }
+        v
+    }
+
+    /// Hand-built counter file: counter `j` of location `i` is `i - j`, widened past `i16`.
+    fn counter_fixture(bits: usize, sample: usize, extra: &str) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        v.extend_from_slice(b"SDM COUNTER\nSDM-Version: v0.0.1\nFormat: binary\n");
+        v.extend_from_slice(b"Order-of-bytes: little-endian\n");
+        v.extend_from_slice(format!("Bits: {}\nSample: {}\n{}\n", bits, sample, extra).as_bytes());
+        for i in 0..sample {
+            for j in 0..bits {
+                let x: i32 = if j == 0 { 100_000 } else { i as i32 - j as i32 };
+                write_i32(&mut v, x).unwrap();
+            }
+        }
+        v
+    }
+
+    #[test]
+    fn test_interop_read_address_space_fixture() {
+        let bits = BitTable::new().len();
+        let len = bits / WORD_BITS;
+        let data = address_space_fixture(bits, len, ADD_DIM);
+        let a = read_address_space(&mut Cursor::new(data)).unwrap();
+        for i in 0..ADD_DIM {
+            let bt = a.get(i);
+            // The least significant bit of the first word is bit 63.
+            assert_eq!(bt.get(63), i & 1 == 1);
+            assert_eq!(bt.get(62), i & 2 == 2);
+            assert!((64..128).all(|j| bt.get(j)));
+        }
+    }
+
+    #[test]
+    fn test_interop_read_counter_fixture() {
+        let bits = BitTable::new().len();
+        let data = counter_fixture(bits, ADD_DIM, "");
+        let c = read_counter(&mut Cursor::new(data)).unwrap();
+        assert_eq!(c.get(0, 0), i16::max_value());
+        assert_eq!(c.get(ADD_DIM-1, 1), (ADD_DIM - 2) as i16);
+        assert_eq!(c.get(0, bits-1), -(bits as i16 - 1));
+        // Files of earlier versions of this crate declare the counter size.
+        let old = read_counter(&mut Cursor::new(counter_fixture(bits, ADD_DIM, "Counter-Size: 4\n"))).unwrap();
+        assert_eq!(old.as_slice(), c.as_slice());
+        let e = read_counter(&mut Cursor::new(counter_fixture(bits, ADD_DIM, "Counter-Size: 8\n"))).err().unwrap();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_interop_words_match_c_bitstring() {
+        // Bits set with the C library's `bs_set_bit` land where `to_words` puts them.
+        let bt = BitTable::new_rand();
+        let mut bs = Bitstring::zeros(bt.len());
+        for i in (0..bt.len()).filter(|&i| bt.get(i)) { bs.set(i, true); }
+        assert_eq!(to_words(&bt), bs.as_slice());
+        assert_eq!(from_words(bs.as_slice(), bt.len()), bt);
+    }
+
+    #[test]
+    fn test_interop_round_trip() {
+        let a = AddressSpace::new();
+        let mut buf: Vec<u8> = Vec::new();
+        write_address_space(&a, &mut buf).unwrap();
+        let b = read_address_space(&mut Cursor::new(buf)).unwrap();
+        for i in 0..ADD_DIM { assert_eq!(a.get(i), b.get(i)); }
+
+        let mut c = Counter::new();
+        c.input(3, a.get(0));
+        c.set(7, 5, false);
+        let mut buf: Vec<u8> = Vec::new();
+        write_counter(&c, &mut buf).unwrap();
+        let d = read_counter(&mut Cursor::new(buf)).unwrap();
+        assert_eq!(c.as_slice(), d.as_slice());
+    }
+
+    #[test]
+    fn test_interop_mismatch() {
+        let bits = BitTable::new().len();
+        let len = bits / WORD_BITS;
+        let data = address_space_fixture(bits, len, ADD_DIM + 1);
+        let e = read_address_space(&mut Cursor::new(data)).err().unwrap();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+        let data = counter_fixture(bits + 1, ADD_DIM, "");
+        let e = read_counter(&mut Cursor::new(data)).err().unwrap();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+        let e = read_counter(&mut Cursor::new(b"SDM ADDRESS SPACE\n\n".to_vec())).err().unwrap();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_interop_truncated() {
+        let bits = BitTable::new().len();
+        let mut data = address_space_fixture(bits, bits / WORD_BITS, ADD_DIM);
+        data.pop();
+        let e = read_address_space(&mut Cursor::new(data)).err().unwrap();
+        assert_eq!(e.kind(), ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,168 @@
+//
+// Item memory (codebook) and cleanup memory for symbolic atoms.
+//
+// Each symbol gets a random hypervector, created on first use from a seed derived from
+// the memory seed and the symbol name, so that two item memories with the same seed
+// agree on every symbol.
+//
+///
+use std::collections::HashMap;
+///
+use bittable::BitTable;
+///
+use vsa;
+///
+use SDM;
+///
+/// Seed of the hypervector of `name`: FNV-1a of the name, mixed with the memory seed.
+///
+fn symbol_seed(seed: u64, name: &str) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in name.bytes() {
+        h ^= u64::from(b);
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    h ^ seed.wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+///
+/// Codebook of symbols, with nearest-neighbor cleanup.
+///
+/// When backed by an `SDM`, every new symbol is also written autoassociatively
+/// into the SDM, and `cleanup` reads the SDM before searching the codebook.
+///
+#[derive(Clone, Debug)]
+pub struct ItemMemory {
+    seed: u64,
+    names: Vec<String>,
+    vectors: Vec<BitTable>,
+    index: HashMap<String, usize>,
+    sdm: Option<SDM>,
+    radius: usize,
+}
+///
+///
+///
+impl ItemMemory {
+    ///
+    /// An empty item memory.
+    ///
+    pub fn new(seed: u64) -> Self {
+        ItemMemory { seed, names: Vec::new(), vectors: Vec::new(), index: HashMap::new(), sdm: None, radius: 0 }
+    }
+    ///
+    /// An empty item memory backed by `sdm`, read and written with `radius`.
+    ///
+    pub fn with_sdm(seed: u64, sdm: SDM, radius: usize) -> Self {
+        ItemMemory { sdm: Some(sdm), radius, ..ItemMemory::new(seed) }
+    }
+    ///
+    /// Number of symbols.
+    ///
+    pub fn len(&self) -> usize { self.names.len() }
+    ///
+    ///
+    ///
+    pub fn is_empty(&self) -> bool { self.names.is_empty() }
+    ///
+    /// Names of the symbols, in creation order.
+    ///
+    pub fn names(&self) -> &[String] { &self.names }
+    ///
+    /// The hypervector of `name`, created if needed.
+    ///
+    pub fn get(&mut self, name: &str) -> &BitTable {
+        let i = match self.index.get(name) {
+            Some(&i) => i,
+            None => self.insert(name),
+        };
+        &self.vectors[i]
+    }
+    ///
+    /// The hypervector of `name`, if it exists.
+    ///
+    pub fn lookup(&self, name: &str) -> Option<&BitTable> {
+        self.index.get(name).map(|&i| &self.vectors[i])
+    }
+    ///
+    ///
+    ///
+    fn insert(&mut self, name: &str) -> usize {
+        let v = vsa::random(symbol_seed(self.seed, name));
+        if let Some(ref mut sdm) = self.sdm { sdm.write(&v, self.radius); }
+        self.names.push(name.to_string());
+        self.vectors.push(v);
+        self.index.insert(name.to_string(), self.names.len() - 1);
+        self.names.len() - 1
+    }
+    ///
+    /// The `k` symbols closest to `query`, with their distances, closest first.
+    ///
+    pub fn nearest(&self, query: &BitTable, k: usize) -> Vec<(String, usize)> {
+        let mut res: Vec<(String, usize)> = self.names.iter().cloned()
+            .zip(self.vectors.iter().map(|v| v.distance(query)))
+            .collect();
+        res.sort_by_key(|&(_, d)| d);
+        res.truncate(k);
+        res
+    }
+    ///
+    /// Clean up a noisy `query`: read the SDM if any, then return the `k` closest symbols.
+    ///
+    pub fn cleanup(&self, query: &BitTable, k: usize) -> Vec<(String, usize)> {
+        let cleaned = match self.sdm {
+            Some(ref sdm) => sdm.read(query, self.radius),
+            None => query.clone(),
+        };
+        self.nearest(&cleaned, k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::ItemMemory;
+    use super::BitTable;
+    use noise::Noise;
+    use address_space::AddressSpace;
+    use counter::Counter;
+    use SDM;
+
+    const SYMBOLS: [&str; 8] = ["red", "green", "blue", "circle", "square", "small", "large", "x"];
+
+    #[test]
+    fn test_item_memory_lazy_seeded() {
+        let mut a = ItemMemory::new(1);
+        let mut b = ItemMemory::new(1);
+        assert!(a.lookup("red").is_none());
+        let red = a.get("red").clone();
+        assert_eq!(a.len(), 1);
+        assert_eq!(a.get("red"), &red);
+        assert_eq!(a.len(), 1);
+        assert_eq!(b.get("red"), &red);
+        assert!(ItemMemory::new(2).get("red") != &red);
+        assert!(a.get("blue") != &red);
+    }
+
+    #[test]
+    fn test_item_memory_nearest() {
+        let mut m = ItemMemory::new(3);
+        for s in SYMBOLS.iter() { m.get(s); }
+        let query = m.get("blue").flip_bits(20, 5);
+        let top = m.nearest(&query, 3);
+        assert_eq!(top.len(), 3);
+        assert_eq!(top[0], ("blue".to_string(), 20));
+        assert!(top[0].1 <= top[1].1 && top[1].1 <= top[2].1);
+        assert_eq!(m.nearest(&query, 100).len(), SYMBOLS.len());
+    }
+
+    #[test]
+    fn test_item_memory_cleanup_sdm() {
+        let radius = 54;
+        let mut m = ItemMemory::with_sdm(4, SDM::from_parts(AddressSpace::with_seed(4), Counter::new()), radius);
+        for s in SYMBOLS.iter().take(4) { m.get(s); }
+        let original: BitTable = m.get("green").clone();
+        let query = original.flip_bits(8, 6);
+        let top = m.cleanup(&query, 1);
+        assert_eq!(top[0].0, "green");
+    }
+}
@@ -50,6 +50,11 @@ include!(concat!(env!("OUT_DIR"), "/dimensions.rs"));
 extern crate rand;
 extern crate libc;
 extern crate bittable;
+#[cfg(feature = "server")]
+extern crate tiny_http;
+#[cfg(feature = "server")]
+#[macro_use]
+extern crate serde_json;
 ///
 ///
 ///
@@ -59,22 +64,127 @@ pub mod address_space;
 ///
 pub mod counter;
 ///
+/// Lock-free saturating counters.
+///
+pub mod atomic_counter;
+///
+/// Theoretical analysis: activation, overlap, capacity, critical distance.
+///
+pub mod analysis;
+///
+/// Statistics on the state and the use of a memory.
+///
+pub mod stats;
+///
+/// Seeded noise injection for cues and data.
+///
+pub mod noise;
+///
+/// Safe wrapper over the C bitstring library.
+///
+pub mod bitstring;
+///
+/// `BitTable` wrapper with the operators of a bit-vector.
+///
+pub mod vector;
+///
+/// Bit-vector as an array of `bool`.
+///
+pub mod array;
+///
+/// Trait common to the bit-vector types, over which `SDM` is generic.
+///
+pub mod hypervector;
+///
+/// Hex and Base64 text encoding of addresses.
+///
+pub mod encoding;
+///
+/// Import and export of the C `sdm` library's memory files.
+///
+pub mod interop;
+///
+/// Binary spatter code algebra: bind, bundle, permute, similarity.
+///
+pub mod vsa;
+///
+/// Item memory and cleanup memory for symbolic atoms.
+///
+pub mod item_memory;
+///
+/// Record encoding with role-filler binding.
+///
+pub mod record;
+///
+/// Encoders of values into addresses.
+///
+pub mod encoder;
+///
+/// Native persisted memory format.
+///
+pub mod store;
+///
+/// Merge of memories trained on disjoint data.
+///
+pub mod merge;
+///
+/// Write-ahead log with compaction and crash recovery.
+///
+pub mod wal;
+///
+/// Line-delimited pattern files for bulk import and export.
+///
+pub mod patterns;
+///
+/// SDM shared between threads, with sharded counter locks or atomic counters.
+///
+pub mod shared;
+///
+/// Snapshots and copy-on-write forks.
+///
+pub mod cow;
+///
+/// Memory split into shards held by worker processes.
+///
+pub mod distributed;
+///
+/// C ABI of the staticlib build.
+///
+pub mod capi;
+///
+/// HTTP/JSON service hosting named memories.
+///
+#[cfg(feature = "server")]
+pub mod server;
+///
+/// Prometheus metrics of the memory operations.
+///
+#[cfg(feature = "metrics")]
+pub mod metrics;
+///
 use rand::{Rng, thread_rng};
 ///
 use address_space::AddressSpace;
 ///
 use counter::Counter;
 ///
+use merge::MergeError;
+///
+use stats::{Activity, Stats};
+///
 use bittable::BitTable;
+///
+use hypervector::HyperVector;
 /// 
-/// Structure SDM.
+/// Structure SDM, over addresses of any `HyperVector` representation.
 ///
 #[derive(Clone, Debug)]
-pub struct SDM {
+pub struct SDM<V = BitTable> {
     d: usize,
     hl: usize,
-    a: AddressSpace,
+    a: AddressSpace<V>,
     c: Counter,
+    activity: Activity,
 }
 ///
 ///
@@ -84,66 +194,156 @@ impl SDM {
     ///
     ///
     pub fn new() -> Self {
-        SDM { d: DIM, hl: ADD_DIM, a: AddressSpace::new(), c: Counter::new() }
+        SDM { d: DIM, hl: ADD_DIM, a: AddressSpace::new(), c: Counter::new(), activity: Activity::new() }
     }
+}
+///
+///
+///
+impl<V: HyperVector> SDM<V> {
     ///
+    /// Build an SDM from an existing address space and counters.
     ///
+    /// Panics if the addresses do not have one bit per counter of a hard location.
     ///
-    pub fn scan(&self, b: &BitTable, radius: usize) -> Vec<usize> {
+    pub fn from_parts(a: AddressSpace<V>, c: Counter) -> Self {
+        assert_eq!(a.get(0).len() * ADD_DIM, c.len(), "addresses of {} bits for {} counters per location",
+            a.get(0).len(), c.len() / ADD_DIM);
+        SDM { d: DIM, hl: ADD_DIM, a, c, activity: Activity::new() }
+    }
+    ///
+    /// An empty SDM over the hard locations `a`, with one counter per bit of its addresses.
+    ///
+    pub fn with_address_space(a: AddressSpace<V>) -> Self {
+        let bits = a.get(0).len();
+        SDM::from_parts(a, Counter::with_bits(bits))
+    }
+    ///
+    ///
+    ///
+    pub fn address_space(&self) -> &AddressSpace<V> { &self.a }
+    ///
+    ///
+    ///
+    pub fn counter(&self) -> &Counter { &self.c }
+    ///
+    ///
+    ///
+    pub(crate) fn counter_mut(&mut self) -> &mut Counter { &mut self.c }
+    ///
+    ///
+    ///
+    pub fn scan(&self, b: &V, radius: usize) -> Vec<usize> {
         AddressSpace::radius_fit(&self.a, b, radius)
     }
     ///
     ///
     ///
-    pub fn write(&mut self, b: &BitTable, radius: usize) {
-        let selected: Vec<usize> = AddressSpace::radius_fit(&self.a, b, radius);
-        println!("WRITE selected len = {}, selected = {:?}\n", selected.len(), selected);
+    pub fn write(&mut self, b: &V, radius: usize) {
+        self.write_data(b, b, radius)
+    }
+    ///
+    /// Write `data` at the hard locations activated by `address`.
+    ///
+    pub fn write_data(&mut self, address: &V, data: &V, radius: usize) {
+        #[cfg(feature = "metrics")]
+        let started = metrics::start();
+        let selected: Vec<usize> = AddressSpace::radius_fit(&self.a, address, radius);
+        #[cfg(feature = "metrics")]
+        {
+            metrics::scanned(started, &selected);
+            metrics::WRITES.inc();
+        }
+        self.activity.write(&selected);
         for i in 0..ADD_DIM { 
-            // Get the address inside radius.
-            let bt = self.a.get(i);
+            // Skip the addresses outside radius.
+            if selected[i] == 0 { continue; }
             // Update counters at the address.
-            self.c.input(i, bt); 
-            println!("WRITE i = {}", i);
+            self.c.input(i, data); 
         }
     }
     ///
+    /// Add the counters of `other`, which must have the same hard locations, saturating
+    /// at the bounds of the counters. Returns the number of counters that saturated.
+    ///
+    pub fn merge(&mut self, other: &SDM<V>) -> Result<usize, MergeError> {
+        if let Some(location) = (0..ADD_DIM).find(|&i| self.a.get(i) != other.a.get(i)) {
+            return Err(MergeError::AddressSpace { location });
+        }
+        self.activity.merge(&other.activity);
+        Ok(self.c.merge(&other.c))
+    }
+    ///
+    /// Reset all the counters and the activity, keeping the hard locations.
+    ///
+    pub fn clear(&mut self) {
+        self.c = Counter::with_bits(self.c.bits());
+        self.activity = Activity::new();
+    }
+    ///
+    /// Report on the counters and on the writes and reads since the memory was built
+    /// or cleared.
+    ///
+    pub fn stats(&self) -> Stats { self.activity.stats(&self.c) }
     ///
     ///
-    pub fn read(&mut self, b: &BitTable, radius: usize) -> BitTable {
+    ///
+    pub fn read(&self, b: &V, radius: usize) -> V {
+        #[cfg(feature = "metrics")]
+        let started = metrics::start();
         let selected: Vec<usize> = AddressSpace::radius_fit(&self.a, b, radius);
-        println!("READ selected len = {}, selected = {:?}\n", selected.len(), selected);
-        let mut sum: Vec<i16> = vec![0;b.len()];
+        #[cfg(feature = "metrics")]
+        {
+            metrics::scanned(started, &selected);
+            metrics::READS.inc();
+        }
+        self.activity.read(selected.iter().filter(|&&s| s != 0).count());
+        let mut sum: Vec<i32> = vec![0;b.len()];
         let mut counters: Vec<i16>;
-        let rng = &mut thread_rng();
-        let mut bt: BitTable = BitTable::new();
         // For all selected addresses
         for i in 0..ADD_DIM { 
+            if selected[i] == 0 { continue; }
             // Counters for selected address `i`.
             counters = self.c.get_counters(i);
             // Sum the contents of the `i`th buckets
             for count in 0..counters.len() { 
-                sum[count] += counters[count];
+                sum[count] += i32::from(counters[count]);
             }
         }
-        println!("\n===================================\n");
-        println!("\nSum[{:?}]", sum);
-        println!("]\n===================================\n");
-        // Threshold the sum to either 1 or 0 based on whether the sum is positive or negative
-        for i in 0..sum.len() { 
-            if sum[i] > 0 { sum[i] = 1; } 
-            else if sum[i] < 0 { sum[i] = 0; } 
-            else if sum[i] == 0 { 
-                let v: bool=rng.gen(); 
-                if v { sum[i] = 1;} else { sum[i] = 0; } 
-            } 
+        threshold(&sum)
+    }
+    ///
+    /// Read iteratively from `cue`, each read being the cue of the next one, until a
+    /// read returns its own cue or after `steps` reads.
+    ///
+    /// Returns the last read and the number of reads.
+    ///
+    pub fn recall(&self, cue: &V, radius: usize, steps: usize) -> (V, usize) {
+        let mut current = cue.clone();
+        let mut done = 0;
+        while done < steps {
+            let next = self.read(&current, radius);
+            done += 1;
+            let fixed = next == current;
+            current = next;
+            if fixed { break; }
         }
-        println!("\n===================================\n");
-        println!("\nThreshold[{:?}]", sum);
-        println!("]\n===================================\n");
-        for i in 0..sum.len() { if sum[i]>0 { bt.set(i) }}
-        bt
+        #[cfg(feature = "metrics")]
+        metrics::RECALL_DISTANCE.observe(cue.distance(&current));
+        (current, done)
     }
 }
+///
+/// Threshold the summed counters of a read: `1` where the sum is positive, `0` where
+/// it is negative, a random bit where it is zero.
+///
+pub(crate) fn threshold<V: HyperVector>(sum: &[i32]) -> V {
+    let rng = &mut thread_rng();
+    let bits: Vec<bool> = sum.iter()
+        .map(|&s| if s > 0 { true } else if s < 0 { false } else { rng.gen() })
+        .collect();
+    V::from_bools(&bits)
+}
 
 #[cfg(test)]
 mod tests {
@@ -156,6 +356,15 @@ mod tests {
         assert_eq!(s.d, DIM);
     }
 
+    #[test]
+    fn test_sdm_write_read() {
+        let bt = BitTable::new_rand();
+        let radius: usize = 54;
+        let mut s: SDM = SDM::new();
+        s.write(&bt, radius);
+        assert_eq!(s.read(&bt, radius), bt);
+    }
+
     #[test]
     fn test_sdm_scan() {
         let bt = BitTable::new_rand();
@@ -174,6 +383,20 @@ mod tests {
         assert_eq!(s.d, DIM);
     }
 
+    #[test]
+    fn test_sdm_recall() {
+        use noise::Noise;
+        use vsa;
+        let mut s = SDM::from_parts(AddressSpace::with_seed(8), Counter::new());
+        for k in 0..10 { s.write(&vsa::random(k), 54); }
+        let x = vsa::random(1);
+        let (y, steps) = s.recall(&x.flip_bits(12, 3), 54, 10);
+        assert_eq!(y, x);
+        assert!(steps >= 2 && steps <= 10);
+        assert_eq!(s.recall(&x, 54, 0), (x.clone(), 0));
+        assert_eq!(s.recall(&x, 54, 10), (x, 1));
+    }
+
     #[test]
     fn test_sdm_read() {
         let mut bt = BitTable::new_rand();
@@ -189,6 +412,48 @@ mod tests {
         println!("READ distance = {}", x);
     }
 
+    fn same_memory<V: HyperVector>(seed: u64) {
+        use hypervector::convert;
+        use vsa;
+        let bits = BitTable::new().len();
+        let mut s = SDM::from_parts(AddressSpace::with_seed(seed), Counter::new());
+        let mut t: SDM<V> = SDM::from_parts(AddressSpace::seeded(bits, seed), Counter::new());
+        for k in 0..3 {
+            s.write(&vsa::random(k), 54);
+            t.write(&convert(&vsa::random(k)), 54);
+        }
+        assert_eq!(t.counter().as_slice(), s.counter().as_slice());
+        let x: V = convert(&vsa::random(1));
+        assert_eq!(t.read(&x, 54), x);
+    }
+
+    #[test]
+    fn test_sdm_hypervector() {
+        use array::BoolArray;
+        use bitstring::Bitstring;
+        use vector::Vector;
+        same_memory::<BoolArray>(3);
+        same_memory::<Vector>(3);
+        same_memory::<Bitstring>(3);
+    }
+
+    #[test]
+    fn test_sdm_bitstring_dimension() {
+        use analysis::default_radius;
+        use bitstring::Bitstring;
+        let bits = 1000;
+        let radius = default_radius(bits, ADD_DIM);
+        let mut s: SDM<Bitstring> = SDM::with_address_space(AddressSpace::seeded(bits, 5));
+        assert_eq!(s.counter().bits(), bits);
+        let patterns: Vec<Bitstring> = (0..3).map(|_| Bitstring::random(bits)).collect();
+        for p in patterns.iter() { s.write(p, radius); }
+        for p in patterns.iter() { assert_eq!(s.read(p, radius), *p); }
+        let mut cue = patterns[0].clone();
+        cue.flip_random_bits(20);
+        assert_eq!(s.recall(&cue, radius, 10).0, patterns[0]);
+        s.clear();
+        assert_eq!(s.counter().bits(), bits);
+    }
 
 }
 /*
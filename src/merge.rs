@@ -0,0 +1,103 @@
+//
+// Merge of memories trained separately on the same hard locations.
+//
+// Writes only add to the counters, so memories drawn from the same address-space seed
+// and written with the same radius can be trained on disjoint data by separate
+// workers, then merged by summing their counters. Counters saturate instead of
+// overflowing; the number of saturated counters is returned.
+//
+///
+use std::error::Error;
+///
+use std::fmt;
+///
+use std::fs::File;
+///
+use std::io::{self, BufReader};
+///
+use std::path::Path;
+///
+use store::Memory;
+///
+/// Error raised when merging memories.
+///
+#[derive(Debug)]
+pub enum MergeError {
+    ///
+    /// Reading a memory failed.
+    ///
+    Io(io::Error),
+    ///
+    /// The memories do not have the same hard locations.
+    ///
+    AddressSpace {
+        ///
+        /// First hard location that differs.
+        ///
+        location: usize,
+    },
+    ///
+    /// The memories were written with different parameters.
+    ///
+    Parameter {
+        ///
+        name: &'static str,
+        ///
+        left: String,
+        ///
+        right: String,
+    },
+}
+///
+///
+///
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MergeError::Io(ref e) => write!(f, "{}", e),
+            MergeError::AddressSpace { location } => write!(f, "hard location {} differs", location),
+            MergeError::Parameter { name, ref left, ref right } => write!(f, "{} differs: {} and {}", name, left, right),
+        }
+    }
+}
+///
+///
+///
+impl Error for MergeError {
+    fn description(&self) -> &str { "memories cannot be merged" }
+}
+///
+///
+///
+impl From<io::Error> for MergeError {
+    fn from(e: io::Error) -> Self { MergeError::Io(e) }
+}
+///
+/// Check that parameter `name` is equal in both memories.
+///
+pub(crate) fn same<T: PartialEq + fmt::Debug>(name: &'static str, left: T, right: T) -> Result<(), MergeError> {
+    if left == right { return Ok(()); }
+    Err(MergeError::Parameter { name, left: format!("{:?}", left), right: format!("{:?}", right) })
+}
+///
+/// Merge the memories saved at `paths`, streaming all but the first from disk.
+///
+/// Returns the merged memory and the number of counters that saturated.
+///
+pub fn merge_files<P: AsRef<Path>>(paths: &[P]) -> Result<(Memory, usize), MergeError> {
+    let (first, rest) = match paths.split_first() {
+        Some(split) => split,
+        None => return Err(MergeError::Io(io::Error::new(io::ErrorKind::InvalidInput, "no memory to merge"))),
+    };
+    let with_path = |p: &Path, e: io::Error| io::Error::new(e.kind(), format!("{}: {}", p.display(), e));
+    let mut merged = Memory::load(first).map_err(|e| with_path(first.as_ref(), e))?;
+    let mut saturated = 0;
+    for p in rest {
+        let f = File::open(p).map_err(|e| with_path(p.as_ref(), e))?;
+        saturated += match merged.merge_from(&mut BufReader::new(f)) {
+            Err(MergeError::Io(e)) => return Err(MergeError::Io(with_path(p.as_ref(), e))),
+            other => other?,
+        };
+    }
+    Ok((merged, saturated))
+}
@@ -0,0 +1,202 @@
+//
+// Prometheus metrics of the memory operations (feature `metrics`).
+//
+// The metrics are process-wide statics updated with relaxed atomics: counters of the
+// writes and reads, and histograms of the scan latency, of the number of hard locations
+// activated by a scan, and of the distance between the cue and the result of an
+// iterative read (`SDM::recall`). `render` formats them in the Prometheus text
+// exposition format, served by `kanerva-server` at `GET /metrics`.
+//
+// Without the feature the module is not built and the operations are not instrumented.
+//
+///
+use std::fmt::{self, Write};
+///
+use std::sync::atomic::{AtomicUsize, Ordering};
+///
+use std::time::Instant;
+///
+/// Most buckets of a histogram, `+Inf` excluded.
+///
+const BUCKETS: usize = 16;
+///
+/// Initial value of the atomics; a `const`, copied into each of them.
+///
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicUsize = AtomicUsize::new(0);
+///
+/// Monotonic counter.
+///
+#[derive(Debug)]
+pub struct Total {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicUsize,
+}
+///
+///
+///
+impl Total {
+    ///
+    ///
+    ///
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Total { name, help, value: ZERO }
+    }
+    ///
+    ///
+    ///
+    pub fn inc(&self) { self.value.fetch_add(1, Ordering::Relaxed); }
+    ///
+    ///
+    ///
+    pub fn get(&self) -> usize { self.value.load(Ordering::Relaxed) }
+    ///
+    ///
+    ///
+    fn render(&self, out: &mut String) -> fmt::Result {
+        write!(out, "# HELP {} {}\n# TYPE {} counter\n", self.name, self.help, self.name)?;
+        write!(out, "{} {}\n", self.name, self.get())
+    }
+}
+///
+/// Histogram of integer observations, exposed divided by `scale` (e.g. microseconds
+/// exposed as seconds with a scale of `1e6`).
+///
+#[derive(Debug)]
+pub struct Histogram {
+    name: &'static str,
+    help: &'static str,
+    scale: f64,
+    bounds: &'static [usize],
+    buckets: [AtomicUsize; BUCKETS],
+    count: AtomicUsize,
+    sum: AtomicUsize,
+}
+///
+///
+///
+impl Histogram {
+    ///
+    /// Histogram with the increasing upper `bounds` (at most 16), in observation units.
+    ///
+    pub const fn new(name: &'static str, help: &'static str, scale: f64, bounds: &'static [usize]) -> Self {
+        Histogram { name, help, scale, bounds, buckets: [ZERO; BUCKETS], count: ZERO, sum: ZERO }
+    }
+    ///
+    ///
+    ///
+    pub fn observe(&self, x: usize) {
+        if let Some(k) = self.bounds.iter().take(BUCKETS).position(|&b| x <= b) {
+            self.buckets[k].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(x, Ordering::Relaxed);
+    }
+    ///
+    /// Number of observations.
+    ///
+    pub fn count(&self) -> usize { self.count.load(Ordering::Relaxed) }
+    ///
+    /// Sum of the observations, in observation units.
+    ///
+    pub fn sum(&self) -> usize { self.sum.load(Ordering::Relaxed) }
+    ///
+    ///
+    ///
+    fn render(&self, out: &mut String) -> fmt::Result {
+        write!(out, "# HELP {} {}\n# TYPE {} histogram\n", self.name, self.help, self.name)?;
+        let mut cumulated = 0;
+        for (b, n) in self.bounds.iter().zip(self.buckets.iter()) {
+            cumulated += n.load(Ordering::Relaxed);
+            write!(out, "{}_bucket{{le=\"{}\"}} {}\n", self.name, *b as f64 / self.scale, cumulated)?;
+        }
+        // Read the count last, so that `+Inf` is at least the last bucket.
+        let count = self.count().max(cumulated);
+        write!(out, "{}_bucket{{le=\"+Inf\"}} {}\n", self.name, count)?;
+        write!(out, "{}_sum {}\n", self.name, self.sum() as f64 / self.scale)?;
+        write!(out, "{}_count {}\n", self.name, count)
+    }
+}
+///
+///
+///
+pub static WRITES: Total = Total::new("kanerva_writes_total", "Writes into a memory.");
+///
+///
+///
+pub static READS: Total = Total::new("kanerva_reads_total", "Reads from a memory, each step of an iterative read included.");
+///
+///
+///
+pub static SCAN_SECONDS: Histogram = Histogram::new("kanerva_scan_seconds",
+    "Time to find the hard locations activated by an address.", 1e6,
+    &[10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000, 1_000_000]);
+///
+///
+///
+pub static ACTIVATED: Histogram = Histogram::new("kanerva_activated_locations",
+    "Hard locations activated by a write or a read.", 1.0,
+    &[0, 1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 2_000, 5_000, 10_000, 100_000, 1_000_000]);
+///
+///
+///
+pub static RECALL_DISTANCE: Histogram = Histogram::new("kanerva_recall_distance",
+    "Hamming distance between the cue and the result of an iterative read.", 1.0,
+    &[0, 1, 2, 4, 8, 16, 32, 64, 128, 256, 512, 1024]);
+///
+/// Start timing a scan.
+///
+pub(crate) fn start() -> Instant { Instant::now() }
+///
+/// Record a scan started at `started`, which activated the locations where `selected`
+/// is not `0`.
+///
+pub(crate) fn scanned(started: Instant, selected: &[usize]) {
+    let elapsed = started.elapsed();
+    SCAN_SECONDS.observe(elapsed.as_secs() as usize * 1_000_000 + elapsed.subsec_nanos() as usize / 1_000);
+    ACTIVATED.observe(selected.iter().filter(|&&s| s != 0).count());
+}
+///
+/// All the metrics in the Prometheus text exposition format.
+///
+pub fn render() -> String {
+    let mut out = String::new();
+    let rendered = WRITES.render(&mut out)
+        .and_then(|_| READS.render(&mut out))
+        .and_then(|_| SCAN_SECONDS.render(&mut out))
+        .and_then(|_| ACTIVATED.render(&mut out))
+        .and_then(|_| RECALL_DISTANCE.render(&mut out));
+    rendered.expect("formatting into a string");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_metrics_histogram() {
+        static H: Histogram = Histogram::new("h", "Test histogram.", 10.0, &[10, 20]);
+        for &x in [5, 10, 15, 100].iter() { H.observe(x); }
+        let mut out = String::new();
+        H.render(&mut out).unwrap();
+        assert_eq!(out, "# HELP h Test histogram.\n# TYPE h histogram\n\
+            h_bucket{le=\"1\"} 2\nh_bucket{le=\"2\"} 3\nh_bucket{le=\"+Inf\"} 4\nh_sum 13\nh_count 4\n");
+    }
+
+    #[test]
+    fn test_metrics_render() {
+        // Other tests update the same statics concurrently: only check increases.
+        let writes = WRITES.get();
+        WRITES.inc();
+        assert!(WRITES.get() > writes);
+        let out = render();
+        for name in ["kanerva_writes_total", "kanerva_reads_total", "kanerva_scan_seconds_bucket",
+                     "kanerva_activated_locations_count", "kanerva_recall_distance_sum"].iter() {
+            assert!(out.contains(name), "{} missing", name);
+        }
+        assert!(out.lines().all(|l| l.starts_with('#') || l.split(' ').count() == 2));
+    }
+}
@@ -0,0 +1,143 @@
+//
+// Noise injection for cues and data.
+//
+// All helpers are seeded, so that a test can reproduce the exact same noisy cue.
+//
+///
+use rand::{Rng, SeedableRng};
+///
+use rand::rngs::StdRng;
+///
+use bittable::BitTable;
+///
+/// Build a `StdRng` from a `u64` seed.
+///
+/// The seed is spread over the 32 bytes expected by `StdRng::from_seed`.
+///
+pub fn rng_from_seed(seed: u64) -> StdRng {
+    let mut bytes = [0u8; 32];
+    for (i, b) in bytes.iter_mut().enumerate() {
+        let word = seed.rotate_left(8 * (i / 8) as u32) ^ (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        *b = (word >> (8 * (i % 8))) as u8;
+    }
+    StdRng::from_seed(bytes)
+}
+///
+/// Noise injection on a bit-vector.
+///
+/// Every method leaves `self` untouched and returns a noisy copy.
+///
+pub trait Noise {
+    ///
+    /// Flip exactly `k` distinct bits, chosen uniformly at random.
+    /// Panics if `k` exceeds the length.
+    ///
+    fn flip_bits(&self, k: usize, seed: u64) -> Self;
+    ///
+    /// Flip each bit independently with probability `p`.
+    ///
+    fn flip_with_probability(&self, p: f64, seed: u64) -> Self;
+    ///
+    /// Flip a run of `len` consecutive bits starting at a random position.
+    /// The run wraps around the end of the vector.
+    ///
+    fn burst(&self, len: usize, seed: u64) -> Self;
+    ///
+    /// A cue at Hamming distance exactly `d` from `self`.
+    ///
+    fn cue_at_distance(&self, d: usize, seed: u64) -> Self;
+}
+///
+///
+///
+impl Noise for BitTable {
+    fn flip_bits(&self, k: usize, seed: u64) -> Self {
+        let n = self.len();
+        assert!(k <= n, "cannot flip {} bits out of {}", k, n);
+        let mut rng = rng_from_seed(seed);
+        let mut bits: Vec<bool> = self.iter().collect();
+        // Partial Fisher-Yates: the first `k` entries are distinct random indices.
+        let mut indices: Vec<usize> = (0..n).collect();
+        for i in 0..k {
+            let j = rng.gen_range(i, n);
+            indices.swap(i, j);
+            bits[indices[i]] = !bits[indices[i]];
+        }
+        BitTable::from_slice(&bits)
+    }
+
+    fn flip_with_probability(&self, p: f64, seed: u64) -> Self {
+        assert!(p >= 0.0 && p <= 1.0, "probability {} out of [0, 1]", p);
+        let mut rng = rng_from_seed(seed);
+        let bits: Vec<bool> = self.iter().map(|b| if rng.gen_bool(p) { !b } else { b }).collect();
+        BitTable::from_slice(&bits)
+    }
+
+    fn burst(&self, len: usize, seed: u64) -> Self {
+        let n = self.len();
+        assert!(len <= n, "burst of {} bits exceeds length {}", len, n);
+        let mut rng = rng_from_seed(seed);
+        let start = rng.gen_range(0, n);
+        let mut bits: Vec<bool> = self.iter().collect();
+        for i in 0..len {
+            let j = (start + i) % n;
+            bits[j] = !bits[j];
+        }
+        BitTable::from_slice(&bits)
+    }
+
+    fn cue_at_distance(&self, d: usize, seed: u64) -> Self {
+        self.flip_bits(d, seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Noise;
+    use super::BitTable;
+
+    #[test]
+    fn test_noise_flip_bits() {
+        let bt = BitTable::new_rand();
+        for k in vec![0, 1, 10, bt.len()/2, bt.len()] {
+            let noisy = bt.flip_bits(k, 42);
+            assert_eq!(bt.distance(&noisy), k);
+        }
+    }
+
+    #[test]
+    fn test_noise_seeded() {
+        let bt = BitTable::new_rand();
+        assert_eq!(bt.flip_bits(17, 7), bt.flip_bits(17, 7));
+        assert_eq!(bt.flip_with_probability(0.2, 7), bt.flip_with_probability(0.2, 7));
+        assert_eq!(bt.burst(9, 7), bt.burst(9, 7));
+        assert!(bt.flip_bits(17, 7) != bt.flip_bits(17, 8));
+    }
+
+    #[test]
+    fn test_noise_flip_with_probability() {
+        let bt = BitTable::new_rand();
+        assert_eq!(bt.distance(&bt.flip_with_probability(0.0, 1)), 0);
+        assert_eq!(bt.distance(&bt.flip_with_probability(1.0, 1)), bt.len());
+    }
+
+    #[test]
+    fn test_noise_burst() {
+        let bt = BitTable::new_rand();
+        let noisy = bt.burst(12, 3);
+        assert_eq!(bt.distance(&noisy), 12);
+        // The flipped bits are consecutive, modulo the length.
+        let n = bt.len();
+        let flipped: Vec<usize> = (0..n).filter(|&i| bt.get(i) != noisy.get(i)).collect();
+        let gaps = (0..flipped.len()).filter(|&i| (flipped[(i+1) % flipped.len()] + n - flipped[i]) % n != 1).count();
+        assert_eq!(gaps, 1);
+    }
+
+    #[test]
+    fn test_noise_cue_at_distance() {
+        let bt = BitTable::new_rand();
+        let cue = bt.cue_at_distance(20, 11);
+        assert_eq!(bt.distance(&cue), 20);
+    }
+}
@@ -0,0 +1,401 @@
+//
+// Line-delimited pattern files, for bulk import and export of `(address, data)` pairs.
+//
+// Text formats hold one pair per line, `<address> [<data>]`, separated by whitespace;
+// without data the pattern is autoassociative. Empty lines and lines starting with `#`
+// are skipped. Each field is written in one of:
+//
+//   bits:   `len` characters `0` or `1`, bit `0` first.
+//   hex:    `Encoding::to_hex`.
+//   base64: `Encoding::to_base64`.
+//
+// The compact binary format has an `interop`-style header, then fixed-size records of
+// the address words followed by the data words:
+//
+//     KANERVA PATTERNS
+//     Format: binary
+//     Order-of-bytes: little-endian
+//     Bits: <bits>
+//
+//     <address words><data words> ...
+//
+// Readers stream: they hold one line or one record at a time.
+//
+///
+use std::error::Error;
+///
+use std::fmt;
+///
+use std::io::{self, BufRead, Write};
+///
+use std::str::FromStr;
+///
+use bittable::BitTable;
+///
+use encoding::Encoding;
+///
+use interop::{expect_field, from_words, read_header, read_u64, to_words, write_u64};
+///
+/// Format of a pattern file.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PatternFormat {
+    ///
+    /// Text, one `0`/`1` character per bit.
+    ///
+    Bits,
+    ///
+    /// Text, hexadecimal.
+    ///
+    Hex,
+    ///
+    /// Text, Base64.
+    ///
+    Base64,
+    ///
+    /// Compact binary records.
+    ///
+    Binary,
+}
+///
+///
+///
+impl FromStr for PatternFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "bits" => Ok(PatternFormat::Bits),
+            "hex" => Ok(PatternFormat::Hex),
+            "base64" => Ok(PatternFormat::Base64),
+            "binary" => Ok(PatternFormat::Binary),
+            _ => Err(format!("unknown pattern format `{}`, expected bits, hex, base64 or binary", s)),
+        }
+    }
+}
+///
+/// Error while reading a pattern file.
+///
+#[derive(Debug)]
+pub enum PatternError {
+    ///
+    /// The underlying reader failed.
+    ///
+    Io(io::Error),
+    ///
+    /// Malformed content at `line`, the record number in the binary format (both from `1`),
+    /// or in the binary header (`0`).
+    ///
+    Parse {
+        ///
+        ///
+        ///
+        line: usize,
+        ///
+        ///
+        ///
+        message: String,
+    },
+}
+///
+///
+///
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PatternError::Io(ref e) => write!(f, "{}", e),
+            PatternError::Parse { line: 0, ref message } => write!(f, "header: {}", message),
+            PatternError::Parse { line, ref message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+///
+///
+///
+impl Error for PatternError {
+    fn description(&self) -> &str {
+        match *self {
+            PatternError::Io(_) => "i/o error in pattern file",
+            PatternError::Parse { .. } => "malformed pattern",
+        }
+    }
+}
+///
+///
+///
+impl From<io::Error> for PatternError {
+    fn from(e: io::Error) -> Self { PatternError::Io(e) }
+}
+///
+///
+///
+fn address_bits() -> usize { BitTable::new().len() }
+///
+/// Parse one text field.
+///
+fn parse_field(format: PatternFormat, s: &str) -> Result<BitTable, String> {
+    match format {
+        PatternFormat::Bits => {
+            let n = address_bits();
+            if s.len() != n {
+                return Err(format!("expected {} bits, found {}", n, s.len()));
+            }
+            let mut bits: Vec<bool> = Vec::with_capacity(n);
+            for (i, c) in s.chars().enumerate() {
+                match c {
+                    '0' => bits.push(false),
+                    '1' => bits.push(true),
+                    _ => return Err(format!("invalid character `{}` at bit {}", c, i)),
+                }
+            }
+            Ok(BitTable::from_slice(&bits))
+        },
+        PatternFormat::Hex => BitTable::from_hex(s).map_err(|e| e.to_string()),
+        PatternFormat::Base64 => BitTable::from_base64(s).map_err(|e| e.to_string()),
+        PatternFormat::Binary => unreachable!(),
+    }
+}
+///
+/// Format one text field.
+///
+fn format_field(format: PatternFormat, bt: &BitTable) -> String {
+    match format {
+        PatternFormat::Bits => bt.iter().map(|b| if b { '1' } else { '0' }).collect(),
+        PatternFormat::Hex => bt.to_hex(),
+        PatternFormat::Base64 => bt.to_base64(),
+        PatternFormat::Binary => unreachable!(),
+    }
+}
+///
+/// Streaming reader of `(address, data)` pairs.
+///
+#[derive(Debug)]
+pub struct PatternReader<R> {
+    inner: R,
+    format: PatternFormat,
+    line: usize,
+    buf: String,
+}
+///
+///
+///
+impl<R: BufRead> PatternReader<R> {
+    ///
+    /// A reader of `format`; the binary header is read and checked immediately.
+    ///
+    pub fn new(mut inner: R, format: PatternFormat) -> Result<Self, PatternError> {
+        if format == PatternFormat::Binary {
+            let header = read_header(&mut inner, "KANERVA PATTERNS")
+                .map_err(|e| PatternError::Parse { line: 0, message: e.to_string() })?;
+            expect_field(&header, "Bits", address_bits())
+                .map_err(|e| PatternError::Parse { line: 0, message: e.to_string() })?;
+        }
+        Ok(PatternReader { inner, format, line: 0, buf: String::new() })
+    }
+    ///
+    /// Number of the last line (or record) read.
+    ///
+    pub fn line(&self) -> usize { self.line }
+    ///
+    ///
+    ///
+    fn next_text(&mut self) -> Option<Result<(BitTable, BitTable), PatternError>> {
+        loop {
+            self.buf.clear();
+            match self.inner.read_line(&mut self.buf) {
+                Ok(0) => return None,
+                Ok(_) => {},
+                Err(e) => return Some(Err(PatternError::Io(e))),
+            }
+            self.line += 1;
+            let l = self.buf.trim();
+            if l.is_empty() || l.starts_with('#') { continue; }
+            let line = self.line;
+            let err = |message: String| Some(Err(PatternError::Parse { line, message }));
+            let fields: Vec<&str> = l.split_whitespace().collect();
+            if fields.len() > 2 {
+                return err(format!("expected `<address> [<data>]`, found {} fields", fields.len()));
+            }
+            let address = match parse_field(self.format, fields[0]) {
+                Ok(bt) => bt,
+                Err(m) => return err(format!("address: {}", m)),
+            };
+            let data = match fields.get(1) {
+                Some(s) => match parse_field(self.format, s) {
+                    Ok(bt) => bt,
+                    Err(m) => return err(format!("data: {}", m)),
+                },
+                None => address.clone(),
+            };
+            return Some(Ok((address, data)));
+        }
+    }
+    ///
+    ///
+    ///
+    fn next_binary(&mut self) -> Option<Result<(BitTable, BitTable), PatternError>> {
+        match self.inner.fill_buf() {
+            Ok(b) if b.is_empty() => return None,
+            Ok(_) => {},
+            Err(e) => return Some(Err(PatternError::Io(e))),
+        }
+        self.line += 1;
+        let n = address_bits();
+        let mut words: Vec<u64> = vec![0; to_words(&BitTable::new()).len()];
+        let mut pair: Vec<BitTable> = Vec::with_capacity(2);
+        for _ in 0..2 {
+            for w in words.iter_mut() {
+                *w = match read_u64(&mut self.inner) {
+                    Ok(x) => x,
+                    Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                        return Some(Err(PatternError::Parse { line: self.line, message: "truncated record".to_string() }));
+                    },
+                    Err(e) => return Some(Err(PatternError::Io(e))),
+                };
+            }
+            pair.push(from_words(&words, n));
+        }
+        let data = pair.pop().unwrap();
+        let address = pair.pop().unwrap();
+        Some(Ok((address, data)))
+    }
+}
+///
+///
+///
+impl<R: BufRead> Iterator for PatternReader<R> {
+    type Item = Result<(BitTable, BitTable), PatternError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.format {
+            PatternFormat::Binary => self.next_binary(),
+            _ => self.next_text(),
+        }
+    }
+}
+///
+/// Writer of `(address, data)` pairs.
+///
+#[derive(Debug)]
+pub struct PatternWriter<W: Write> {
+    inner: W,
+    format: PatternFormat,
+}
+///
+///
+///
+impl<W: Write> PatternWriter<W> {
+    ///
+    /// A writer of `format`; the binary header is written immediately.
+    ///
+    pub fn new(mut inner: W, format: PatternFormat) -> io::Result<Self> {
+        if format == PatternFormat::Binary {
+            write!(inner, "KANERVA PATTERNS\n")?;
+            write!(inner, "Format: binary\n")?;
+            write!(inner, "Order-of-bytes: little-endian\n")?;
+            write!(inner, "Bits: {}\n", address_bits())?;
+            write!(inner, "\n")?;
+        }
+        Ok(PatternWriter { inner, format })
+    }
+    ///
+    /// Write one pair; text formats write a single field when `data == address`.
+    ///
+    pub fn write(&mut self, address: &BitTable, data: &BitTable) -> io::Result<()> {
+        match self.format {
+            PatternFormat::Binary => {
+                for w in to_words(address).into_iter().chain(to_words(data)) { write_u64(&mut self.inner, w)?; }
+                Ok(())
+            },
+            f if address == data => write!(self.inner, "{}\n", format_field(f, address)),
+            f => write!(self.inner, "{} {}\n", format_field(f, address), format_field(f, data)),
+        }
+    }
+    ///
+    ///
+    ///
+    pub fn flush(&mut self) -> io::Result<()> { self.inner.flush() }
+    ///
+    /// The underlying writer.
+    ///
+    pub fn into_inner(self) -> W { self.inner }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::Cursor;
+    use super::*;
+    use vsa;
+
+    fn round_trip(format: PatternFormat) {
+        let pairs: Vec<(BitTable, BitTable)> = (0..5).map(|k| {
+            let a = vsa::random(k);
+            let d = if k % 2 == 0 { a.clone() } else { vsa::random(k + 100) };
+            (a, d)
+        }).collect();
+        let mut w = PatternWriter::new(Vec::new(), format).unwrap();
+        for &(ref a, ref d) in pairs.iter() { w.write(a, d).unwrap(); }
+        let buf = w.into_inner();
+        let r = PatternReader::new(Cursor::new(buf), format).unwrap();
+        let back: Vec<(BitTable, BitTable)> = r.map(|p| p.unwrap()).collect();
+        assert_eq!(back, pairs);
+    }
+
+    #[test]
+    fn test_patterns_round_trip() {
+        for f in ["bits", "hex", "base64", "binary"].iter() {
+            round_trip(f.parse().unwrap());
+        }
+        assert!("csv".parse::<PatternFormat>().is_err());
+    }
+
+    #[test]
+    fn test_patterns_comments_and_line_numbers() {
+        let a = vsa::random(1).to_hex();
+        let text = format!("# patterns\n\n{}\n{} {}\n{} zz\n{}\n", a, a, a, a, a);
+        let mut r = PatternReader::new(Cursor::new(text.into_bytes()), PatternFormat::Hex).unwrap();
+        assert!(r.next().unwrap().is_ok());
+        assert_eq!(r.line(), 3);
+        assert!(r.next().unwrap().is_ok());
+        match r.next().unwrap() {
+            Err(PatternError::Parse { line, message }) => {
+                assert_eq!(line, 5);
+                assert!(message.starts_with("data:"), "{}", message);
+            },
+            other => panic!("unexpected {:?}", other),
+        }
+        // Reading resumes after a malformed line.
+        assert!(r.next().unwrap().is_ok());
+        assert!(r.next().is_none());
+    }
+
+    #[test]
+    fn test_patterns_bits_errors() {
+        let n = BitTable::new().len();
+        let short = "01".to_string();
+        let bad: String = (0..n).map(|i| if i == 3 { '2' } else { '0' }).collect();
+        for (text, expected) in [(short, "expected"), (bad, "at bit 3")].iter() {
+            let mut r = PatternReader::new(Cursor::new(text.clone().into_bytes()), PatternFormat::Bits).unwrap();
+            let e = r.next().unwrap().err().unwrap();
+            assert!(e.to_string().contains(expected), "{}", e);
+            assert!(e.to_string().starts_with("line 1:"), "{}", e);
+        }
+    }
+
+    #[test]
+    fn test_patterns_binary_truncated() {
+        let mut w = PatternWriter::new(Vec::new(), PatternFormat::Binary).unwrap();
+        w.write(&vsa::random(1), &vsa::random(2)).unwrap();
+        w.write(&vsa::random(3), &vsa::random(4)).unwrap();
+        let mut buf = w.into_inner();
+        buf.pop();
+        let mut r = PatternReader::new(Cursor::new(buf), PatternFormat::Binary).unwrap();
+        assert!(r.next().unwrap().is_ok());
+        match r.next().unwrap() {
+            Err(PatternError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(PatternReader::new(Cursor::new(b"0101\n".to_vec()), PatternFormat::Binary).is_err());
+    }
+}
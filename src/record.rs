@@ -0,0 +1,119 @@
+//
+// Record (key-value structure) encoding with role-filler binding.
+//
+// A record `{role_1: filler_1, ..., role_k: filler_k}` is encoded as
+// `bundle(bind(role_1, filler_1), ..., bind(role_k, filler_k))`.
+// Unbinding a role gives a noisy filler, cleaned up by the item memory.
+//
+///
+use std::collections::HashSet;
+///
+use bittable::BitTable;
+///
+use item_memory::ItemMemory;
+///
+use vsa;
+///
+use SDM;
+///
+/// Encoder and decoder of records, sharing one item memory for roles and fillers.
+///
+#[derive(Clone, Debug)]
+pub struct RecordEncoder {
+    items: ItemMemory,
+    roles: HashSet<String>,
+    seed: u64,
+}
+///
+///
+///
+impl RecordEncoder {
+    ///
+    /// An encoder over `items`; `seed` breaks the ties when bundling an even number of pairs.
+    ///
+    pub fn new(items: ItemMemory, seed: u64) -> Self {
+        RecordEncoder { items, roles: HashSet::new(), seed }
+    }
+    ///
+    ///
+    ///
+    pub fn items(&self) -> &ItemMemory { &self.items }
+    ///
+    /// Encode a record into a single hypervector.
+    ///
+    pub fn encode(&mut self, record: &[(&str, &str)]) -> BitTable {
+        assert!(!record.is_empty(), "cannot encode an empty record");
+        let pairs: Vec<BitTable> = record.iter().map(|&(role, filler)| {
+            self.roles.insert(role.to_string());
+            let r = self.items.get(role).clone();
+            let f = self.items.get(filler);
+            vsa::bind(&r, f)
+        }).collect();
+        vsa::bundle(&pairs, self.seed)
+    }
+    ///
+    /// The filler bound to `role` in `hv`, with its distance, or `None` if no filler is known.
+    ///
+    /// Roles are never returned as fillers.
+    ///
+    pub fn decode(&mut self, hv: &BitTable, role: &str) -> Option<(String, usize)> {
+        let r = self.items.get(role).clone();
+        let noisy = vsa::bind(hv, &r);
+        let n = self.items.len();
+        let roles = &self.roles;
+        self.items.nearest(&noisy, n).into_iter().find(|&(ref name, _)| !roles.contains(name))
+    }
+    ///
+    /// Encode `record` and write it into `sdm`.
+    ///
+    pub fn store(&mut self, sdm: &mut SDM, record: &[(&str, &str)], radius: usize) {
+        let hv = self.encode(record);
+        sdm.write(&hv, radius);
+    }
+    ///
+    /// Read from `sdm` the stored record closest to the partial record `partial`.
+    ///
+    pub fn retrieve(&mut self, sdm: &SDM, partial: &[(&str, &str)], radius: usize) -> BitTable {
+        let hv = self.encode(partial);
+        sdm.read(&hv, radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::RecordEncoder;
+    use address_space::AddressSpace;
+    use counter::Counter;
+    use item_memory::ItemMemory;
+    use SDM;
+
+    #[test]
+    fn test_record_decode() {
+        let mut enc = RecordEncoder::new(ItemMemory::new(1), 1);
+        for f in ["red", "blue", "green", "circle", "square", "small", "large"].iter() { enc.items.get(f); }
+        let hv = enc.encode(&[("color", "red"), ("shape", "circle"), ("size", "large")]);
+        assert_eq!(enc.decode(&hv, "color").unwrap().0, "red");
+        assert_eq!(enc.decode(&hv, "shape").unwrap().0, "circle");
+        assert_eq!(enc.decode(&hv, "size").unwrap().0, "large");
+    }
+
+    #[test]
+    fn test_record_single_pair() {
+        let mut enc = RecordEncoder::new(ItemMemory::new(2), 2);
+        let hv = enc.encode(&[("name", "alice")]);
+        assert_eq!(enc.decode(&hv, "name"), Some(("alice".to_string(), 0)));
+    }
+
+    #[test]
+    fn test_record_sdm_partial_content() {
+        let radius = 54;
+        let mut sdm = SDM::from_parts(AddressSpace::with_seed(3), Counter::new());
+        let mut enc = RecordEncoder::new(ItemMemory::new(3), 3);
+        enc.store(&mut sdm, &[("color", "red"), ("shape", "circle"), ("size", "large")], radius);
+        enc.store(&mut sdm, &[("color", "blue"), ("shape", "square"), ("size", "small")], radius);
+        // Retrieve the first record from two of its fields, and decode the third one.
+        let hv = enc.retrieve(&sdm, &[("color", "red"), ("shape", "circle")], radius);
+        assert_eq!(enc.decode(&hv, "size").unwrap().0, "large");
+    }
+}
@@ -0,0 +1,499 @@
+//
+// HTTP/JSON service hosting named memories (feature `server`).
+//
+//   GET  /memories                    names of the memories
+//   PUT  /memories/<name>             create, body `{"seed": s, "radius": r}` (both optional)
+//   POST /memories/<name>/write       `{"address": a, "data": d}`, `data` defaults to `address`
+//   POST /memories/<name>/read        `{"address": a, "steps": n}`, answers `{"data": d, "distance": k, "steps": s}`
+//                                     after up to `n` iterated reads (default 1)
+//   POST /memories/<name>/erase       `{"address": a, "data": d}` undoes a write, `{"all": true}` clears
+//   GET  /memories/<name>/stats       parameters and usage of the memory
+//   POST /memories/<name>/snapshot    save the memory into the data directory
+//   GET  /metrics                     Prometheus metrics, with feature `metrics`
+//
+// Addresses are hexadecimal, or Base64 with `"encoding": "base64"` in the request body.
+// Errors are answered as `{"error": message}` with a 4xx or 5xx status.
+//
+// Each memory has its own lock, so that requests to different memories run in parallel;
+// a snapshot copies the memory under its lock and writes the file outside of it. Snapshots
+// of one memory are serialized, so that the file always holds the latest one.
+//
+///
+use std::collections::HashMap;
+///
+use std::fmt;
+///
+use std::fs;
+///
+use std::io::{self, Read};
+///
+use std::net::SocketAddr;
+///
+use std::path::PathBuf;
+///
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+///
+use std::thread;
+///
+use serde_json::{self, Value};
+///
+use tiny_http::{self, Header, Response};
+///
+use bittable::BitTable;
+///
+use analysis;
+///
+use encoding::Encoding;
+///
+use store::{hard_locations, Memory};
+///
+#[cfg(feature = "metrics")]
+use metrics;
+///
+/// Largest request body accepted, in bytes.
+///
+const MAX_BODY: u64 = 1 << 20;
+///
+/// Status and JSON body of a response.
+///
+pub type Reply = (u16, Value);
+///
+///
+///
+fn error(status: u16, message: String) -> Reply {
+    (status, json!({ "error": message }))
+}
+///
+/// Memory names are also file names: letters, digits, `-` and `_` only.
+///
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+///
+///
+///
+fn lock<'a>(m: &'a Mutex<Memory>) -> MutexGuard<'a, Memory> {
+    // A panic while holding the lock leaves the memory usable: every update is a counter
+    // increment, there is no invariant to break.
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+///
+/// An address field of `body`, in the encoding of the request.
+///
+fn address_field(body: &Value, key: &str) -> Result<Option<BitTable>, Reply> {
+    let s = match body.get(key) {
+        None | Some(&Value::Null) => return Ok(None),
+        Some(&Value::String(ref s)) => s,
+        Some(_) => return Err(error(400, format!("`{}` must be a string", key))),
+    };
+    let parsed = match body.get("encoding").and_then(|e| e.as_str()).unwrap_or("hex") {
+        "hex" => BitTable::from_hex(s),
+        "base64" => BitTable::from_base64(s),
+        other => return Err(error(400, format!("unknown encoding `{}`", other))),
+    };
+    parsed.map(Some).map_err(|e| error(400, format!("`{}`: {}", key, e)))
+}
+///
+/// `bt` in the encoding of the request.
+///
+fn encode(body: &Value, bt: &BitTable) -> String {
+    match body.get("encoding").and_then(|e| e.as_str()) {
+        Some("base64") => bt.to_base64(),
+        _ => bt.to_hex(),
+    }
+}
+///
+/// Named memories, with an optional data directory for snapshots.
+///
+#[derive(Debug)]
+pub struct Registry {
+    dir: Option<PathBuf>,
+    memories: RwLock<HashMap<String, Arc<Mutex<Memory>>>>,
+    snapshots: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+///
+///
+///
+impl Registry {
+    ///
+    /// A registry loading every `<name>.sdm` of `dir`, if any.
+    ///
+    pub fn new(dir: Option<PathBuf>) -> io::Result<Self> {
+        let mut memories: HashMap<String, Arc<Mutex<Memory>>> = HashMap::new();
+        if let Some(ref d) = dir {
+            fs::create_dir_all(d)?;
+            for entry in fs::read_dir(d)? {
+                let path = entry?.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("sdm") { continue; }
+                let name = match path.file_stem().and_then(|s| s.to_str()) {
+                    Some(n) if valid_name(n) => n.to_string(),
+                    _ => continue,
+                };
+                let m = Memory::load(&path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+                memories.insert(name, Arc::new(Mutex::new(m)));
+            }
+        }
+        Ok(Registry { dir, memories: RwLock::new(memories), snapshots: Mutex::new(HashMap::new()) })
+    }
+    ///
+    ///
+    ///
+    fn get(&self, name: &str) -> Result<Arc<Mutex<Memory>>, Reply> {
+        let memories = self.memories.read().unwrap_or_else(|e| e.into_inner());
+        memories.get(name).cloned().ok_or_else(|| error(404, format!("no memory `{}`", name)))
+    }
+    ///
+    /// Answer a request; `body` is empty or a JSON object.
+    ///
+    pub fn handle(&self, method: &str, path: &str, body: &str) -> Reply {
+        let body: Value = if body.trim().is_empty() {
+            json!({})
+        } else {
+            match serde_json::from_str(body) {
+                Ok(v @ Value::Object(_)) => v,
+                Ok(_) => return error(400, "the body must be a JSON object".to_string()),
+                Err(e) => return error(400, format!("invalid JSON: {}", e)),
+            }
+        };
+        let path = path.split('?').next().unwrap_or("");
+        let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let result = match (method, &parts[..]) {
+            ("GET", &["memories"]) => Ok(self.list()),
+            ("PUT", &["memories", name]) => self.create(name, &body),
+            ("POST", &["memories", name, "write"]) => self.write(name, &body),
+            ("POST", &["memories", name, "read"]) => self.read(name, &body),
+            ("POST", &["memories", name, "erase"]) => self.erase(name, &body),
+            ("GET", &["memories", name, "stats"]) => self.stats(name),
+            ("POST", &["memories", name, "snapshot"]) => self.snapshot(name),
+            _ => Err(error(404, format!("no route for {} {}", method, path))),
+        };
+        result.unwrap_or_else(|e| e)
+    }
+    ///
+    ///
+    ///
+    fn list(&self) -> Reply {
+        let memories = self.memories.read().unwrap_or_else(|e| e.into_inner());
+        let mut names: Vec<&String> = memories.keys().collect();
+        names.sort();
+        (200, json!({ "memories": names }))
+    }
+    ///
+    ///
+    ///
+    fn create(&self, name: &str, body: &Value) -> Result<Reply, Reply> {
+        if !valid_name(name) {
+            return Err(error(400, format!("invalid memory name `{}`", name)));
+        }
+        let number = |key: &str| match body.get(key) {
+            None | Some(&Value::Null) => Ok(None),
+            Some(v) => v.as_u64().map(Some).ok_or_else(|| error(400, format!("`{}` must be a number", key))),
+        };
+        let seed = number("seed")?.unwrap_or(0);
+        let radius = match number("radius")? {
+            Some(r) => r as usize,
+            None => analysis::default_radius(BitTable::new().len(), hard_locations()),
+        };
+        let mut memories = self.memories.write().unwrap_or_else(|e| e.into_inner());
+        if memories.contains_key(name) {
+            return Err(error(409, format!("memory `{}` already exists", name)));
+        }
+        memories.insert(name.to_string(), Arc::new(Mutex::new(Memory::new(seed, radius))));
+        Ok((201, json!({ "name": name, "seed": seed, "radius": radius })))
+    }
+    ///
+    ///
+    ///
+    fn write(&self, name: &str, body: &Value) -> Result<Reply, Reply> {
+        let m = self.get(name)?;
+        let address = address_field(body, "address")?.ok_or_else(|| error(400, "missing `address`".to_string()))?;
+        let data = address_field(body, "data")?.unwrap_or_else(|| address.clone());
+        let mut m = lock(&m);
+        m.write(&address, &data);
+        Ok((200, json!({ "writes": m.writes() })))
+    }
+    ///
+    ///
+    ///
+    fn read(&self, name: &str, body: &Value) -> Result<Reply, Reply> {
+        let m = self.get(name)?;
+        let address = address_field(body, "address")?.ok_or_else(|| error(400, "missing `address`".to_string()))?;
+        let steps = match body.get("steps") {
+            None | Some(&Value::Null) => 1,
+            Some(v) => match v.as_u64() {
+                Some(n) if n > 0 => n as usize,
+                _ => return Err(error(400, "`steps` must be a positive integer".to_string())),
+            },
+        };
+        let (data, steps) = if steps == 1 { (lock(&m).read(&address), 1) } else { lock(&m).recall(&address, steps) };
+        Ok((200, json!({ "data": encode(body, &data), "distance": data.distance(&address), "steps": steps })))
+    }
+    ///
+    ///
+    ///
+    fn erase(&self, name: &str, body: &Value) -> Result<Reply, Reply> {
+        let m = self.get(name)?;
+        if body.get("all") == Some(&Value::Bool(true)) {
+            let mut m = lock(&m);
+            m.clear();
+            return Ok((200, json!({ "writes": m.writes() })));
+        }
+        let address = address_field(body, "address")?
+            .ok_or_else(|| error(400, "missing `address`, or `\"all\": true`".to_string()))?;
+        let data = address_field(body, "data")?.unwrap_or_else(|| address.clone());
+        let mut m = lock(&m);
+        m.erase(&address, &data);
+        Ok((200, json!({ "writes": m.writes() })))
+    }
+    ///
+    ///
+    ///
+    fn stats(&self, name: &str) -> Result<Reply, Reply> {
+        let m = self.get(name)?;
+        let m = lock(&m);
+        let bits = BitTable::new().len();
+        let counters = m.sdm().counter().as_slice();
+        let used = counters.chunks(bits).filter(|c| c.iter().any(|&x| x != 0)).count();
+        let projection = m.projection().map(|p| json!({ "dims": p.dims(), "seed": p.seed(), "version": p.version() }));
+        let s = m.stats();
+        Ok((200, json!({
+            "bits": bits,
+            "locations": m.sdm().address_space().len(),
+            "seed": m.seed(),
+            "radius": m.radius(),
+            "writes": m.writes(),
+            "used_locations": used,
+            "activation_probability": analysis::activation_probability(bits, m.radius()),
+            "reads": s.reads,
+            "mean_activated": s.mean_activated,
+            "saturated": s.saturated,
+            "remaining_capacity": s.remaining_capacity,
+            "overloaded": s.is_overloaded(),
+            "projection": projection,
+        })))
+    }
+    ///
+    ///
+    ///
+    fn snapshot(&self, name: &str) -> Result<Reply, Reply> {
+        let dir = self.dir.as_ref().ok_or_else(|| error(400, "the server has no data directory".to_string()))?;
+        let m = self.get(name)?;
+        let serial = {
+            let mut snapshots = self.snapshots.lock().unwrap_or_else(|e| e.into_inner());
+            snapshots.entry(name.to_string()).or_insert_with(|| Arc::new(Mutex::new(()))).clone()
+        };
+        let _serial = serial.lock().unwrap_or_else(|e| e.into_inner());
+        let copy = lock(&m).clone();
+        let path = dir.join(format!("{}.sdm", name));
+        copy.save(&path).map_err(|e| error(500, format!("{}: {}", path.display(), e)))?;
+        Ok((200, json!({ "path": path.display().to_string(), "writes": copy.writes() })))
+    }
+}
+///
+/// HTTP front end of a `Registry`.
+///
+pub struct Server {
+    http: Arc<tiny_http::Server>,
+    registry: Arc<Registry>,
+}
+///
+///
+///
+impl fmt::Debug for Server {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Server {{ addr: {}, registry: {:?} }}", self.local_addr(), self.registry)
+    }
+}
+///
+///
+///
+impl Server {
+    ///
+    /// Listen on `addr`, e.g. `127.0.0.1:8080`; port `0` picks a free port.
+    ///
+    pub fn bind(addr: &str, registry: Registry) -> io::Result<Self> {
+        let http = tiny_http::Server::http(addr).map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        Ok(Server { http: Arc::new(http), registry: Arc::new(registry) })
+    }
+    ///
+    ///
+    ///
+    pub fn local_addr(&self) -> SocketAddr { self.http.server_addr() }
+    ///
+    /// Serve requests on `threads` worker threads.
+    ///
+    pub fn spawn(&self, threads: usize) -> Vec<thread::JoinHandle<()>> {
+        (0..threads.max(1)).map(|_| {
+            let http = self.http.clone();
+            let registry = self.registry.clone();
+            thread::spawn(move || {
+                for request in http.incoming_requests() { serve(&registry, request); }
+            })
+        }).collect()
+    }
+    ///
+    /// Serve requests on `threads` worker threads, forever.
+    ///
+    pub fn run(&self, threads: usize) {
+        for h in self.spawn(threads) { let _ = h.join(); }
+    }
+}
+///
+/// Answer one HTTP request.
+///
+fn serve(registry: &Registry, mut request: tiny_http::Request) {
+    #[cfg(feature = "metrics")]
+    {
+        if request.method().as_str() == "GET" && request.url() == "/metrics" {
+            let header: Header = "Content-Type: text/plain; version=0.0.4".parse().unwrap();
+            let _ = request.respond(Response::from_string(metrics::render()).with_header(header));
+            return;
+        }
+    }
+    let mut body = String::new();
+    let (status, value) = match request.as_reader().take(MAX_BODY).read_to_string(&mut body) {
+        Ok(_) => registry.handle(request.method().as_str(), request.url(), &body),
+        Err(e) => error(400, format!("unreadable body: {}", e)),
+    };
+    let header: Header = "Content-Type: application/json".parse().unwrap();
+    let response = Response::from_string(value.to_string()).with_status_code(status).with_header(header);
+    // The client may be gone; there is nobody to report the error to.
+    let _ = request.respond(response);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use super::*;
+    use noise::Noise;
+    use vsa;
+
+    fn body(address: &BitTable) -> String {
+        json!({ "address": address.to_hex() }).to_string()
+    }
+
+    #[test]
+    fn test_server_routes() {
+        let r = Registry::new(None).unwrap();
+        let x = vsa::random(1);
+        assert_eq!(r.handle("PUT", "/memories/a", r#"{"seed": 1}"#).0, 201);
+        assert_eq!(r.handle("PUT", "/memories/a", "").0, 409);
+        assert_eq!(r.handle("PUT", "/memories/..", "").0, 400);
+        assert_eq!(r.handle("GET", "/memories", "").1, json!({ "memories": ["a"] }));
+        assert_eq!(r.handle("POST", "/memories/a/write", &body(&x)), (200, json!({ "writes": 1 })));
+        let (status, v) = r.handle("POST", "/memories/a/read", &body(&x));
+        assert_eq!(status, 200);
+        assert_eq!(v["data"], json!(x.to_hex()));
+        assert_eq!(v["distance"], json!(0));
+        let noisy = json!({ "address": x.flip_bits(8, 1).to_hex(), "steps": 5 }).to_string();
+        let (status, v) = r.handle("POST", "/memories/a/read", &noisy);
+        assert_eq!((status, &v["data"]), (200, &json!(x.to_hex())));
+        assert!(v["steps"].as_u64().unwrap() <= 5);
+        let b64 = json!({ "address": x.to_base64(), "encoding": "base64" }).to_string();
+        assert_eq!(r.handle("POST", "/memories/a/read", &b64).1["data"], json!(x.to_base64()));
+        assert_eq!(r.handle("GET", "/memories/a/stats", "").1["writes"], json!(1));
+        assert_eq!(r.handle("POST", "/memories/a/erase", &body(&x)).1["writes"], json!(0));
+        assert_eq!(r.handle("POST", "/memories/a/erase", r#"{"all": true}"#).0, 200);
+    }
+
+    #[test]
+    fn test_server_errors() {
+        let r = Registry::new(None).unwrap();
+        r.handle("PUT", "/memories/a", "");
+        assert_eq!(r.handle("POST", "/memories/b/read", &body(&vsa::random(2))).0, 404);
+        assert_eq!(r.handle("DELETE", "/memories/a", "").0, 404);
+        assert_eq!(r.handle("POST", "/memories/a/read", "{").0, 400);
+        assert_eq!(r.handle("POST", "/memories/a/read", "[]").0, 400);
+        assert_eq!(r.handle("POST", "/memories/a/read", r#"{"address": "00"}"#).0, 400);
+        assert_eq!(r.handle("POST", "/memories/a/erase", "").0, 400);
+        let zero_steps = json!({ "address": vsa::random(2).to_hex(), "steps": 0 }).to_string();
+        assert_eq!(r.handle("POST", "/memories/a/read", &zero_steps).0, 400);
+        assert_eq!(r.handle("POST", "/memories/a/snapshot", "").0, 400);
+    }
+
+    #[test]
+    fn test_server_snapshot_reload() {
+        let dir = env::temp_dir().join(format!("kanerva-server-snapshot-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let x = vsa::random(3);
+        {
+            let r = Registry::new(Some(dir.clone())).unwrap();
+            r.handle("PUT", "/memories/kept", "");
+            r.handle("POST", "/memories/kept/write", &body(&x));
+            assert_eq!(r.handle("POST", "/memories/kept/snapshot", "").0, 200);
+        }
+        let r = Registry::new(Some(dir.clone())).unwrap();
+        assert_eq!(r.handle("GET", "/memories/kept/stats", "").1["writes"], json!(1));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_server_concurrent_snapshots() {
+        let dir = env::temp_dir().join(format!("kanerva-server-snapshots-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let r = Arc::new(Registry::new(Some(dir.clone())).unwrap());
+        r.handle("PUT", "/memories/busy", "");
+        let threads: Vec<_> = (0..4).map(|k| {
+            let r = r.clone();
+            thread::spawn(move || for _ in 0..5 {
+                r.handle("POST", "/memories/busy/write", &body(&vsa::random(k)));
+                assert_eq!(r.handle("POST", "/memories/busy/snapshot", "").0, 200);
+            })
+        }).collect();
+        for t in threads { t.join().unwrap(); }
+        let files: Vec<_> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(files, vec![std::ffi::OsString::from("busy.sdm")]);
+        // The last snapshot holds every write.
+        assert_eq!(Registry::new(Some(dir.clone())).unwrap().handle("GET", "/memories/busy/stats", "").1["writes"], json!(20));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Send one request over a new connection and return the status and body.
+    fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let mut s = TcpStream::connect(addr).unwrap();
+        write!(s, "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+               method, path, body.len(), body).unwrap();
+        let mut response = String::new();
+        s.read_to_string(&mut response).unwrap();
+        let status: u16 = response[9..12].parse().unwrap();
+        let json = &response[response.find("\r\n\r\n").unwrap() + 4..];
+        (status, serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn test_server_local_instance() {
+        let server = Server::bind("127.0.0.1:0", Registry::new(None).unwrap()).unwrap();
+        let addr = server.local_addr();
+        server.spawn(4);
+        assert_eq!(request(addr, "PUT", "/memories/m", r#"{"seed": 4}"#).0, 201);
+        let clients: Vec<thread::JoinHandle<()>> = (0..8).map(|k| thread::spawn(move || {
+            let x = vsa::random(100 + k);
+            assert_eq!(request(addr, "POST", "/memories/m/write", &body(&x)).0, 200);
+            assert_eq!(request(addr, "POST", "/memories/m/read", &body(&x)).0, 200);
+        })).collect();
+        for c in clients { c.join().unwrap(); }
+        assert_eq!(request(addr, "GET", "/memories/m/stats", "").1["writes"], json!(8));
+        assert_eq!(request(addr, "GET", "/nowhere", "").0, 404);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_server_metrics() {
+        let server = Server::bind("127.0.0.1:0", Registry::new(None).unwrap()).unwrap();
+        let addr = server.local_addr();
+        server.spawn(1);
+        request(addr, "PUT", "/memories/m", "");
+        request(addr, "POST", "/memories/m/write", &body(&vsa::random(5)));
+        let mut s = TcpStream::connect(addr).unwrap();
+        write!(s, "GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+        let mut response = String::new();
+        s.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("text/plain; version=0.0.4"));
+        assert!(response.contains("\nkanerva_writes_total "));
+        assert!(response.contains("\nkanerva_scan_seconds_count "));
+    }
+}
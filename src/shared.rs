@@ -0,0 +1,330 @@
+//
+// SDM shared between threads.
+//
+// The hard locations never change after construction, so only the counters need
+// synchronization. They are split into `shards` of consecutive hard locations, each
+// behind its own `RwLock`: a read takes the read lock of every shard it touches, one
+// shard at a time, and a write takes the write locks the same way. Many threads can
+// read while others write to other shards, and no thread ever holds two locks.
+//
+// A read running concurrently with writes sees each write either entirely or not at
+// all within a shard, but may see a write in some shards and not yet in others.
+//
+// `AtomicSdm` takes no lock at all: its counters are an `AtomicCounter`, updated one
+// by one with saturating compare-exchange. Writes never wait for each other, and a
+// concurrent read may see a write on some counters and not yet on others.
+//
+#[allow(missing_docs)]
+include!(concat!(env!("OUT_DIR"), "/dimensions.rs"));
+///
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+///
+use bittable::BitTable;
+///
+use address_space::AddressSpace;
+///
+use atomic_counter::AtomicCounter;
+///
+use counter::Counter;
+///
+use {threshold, SDM};
+///
+/// Default number of counter shards.
+///
+pub const DEFAULT_SHARDS: usize = 16;
+///
+/// An SDM whose reads and writes take `&self`; share it with `Arc<SharedSdm>`.
+///
+#[derive(Debug)]
+pub struct SharedSdm {
+    a: AddressSpace,
+    // Hard locations per shard; the last shard may hold fewer.
+    per_shard: usize,
+    // Counters of the hard locations of each shard, row by row.
+    shards: Vec<RwLock<Vec<i16>>>,
+}
+///
+///
+///
+fn read_lock<'a>(shard: &'a RwLock<Vec<i16>>) -> RwLockReadGuard<'a, Vec<i16>> {
+    shard.read().unwrap_or_else(|e| e.into_inner())
+}
+///
+///
+///
+fn write_lock<'a>(shard: &'a RwLock<Vec<i16>>) -> RwLockWriteGuard<'a, Vec<i16>> {
+    shard.write().unwrap_or_else(|e| e.into_inner())
+}
+///
+///
+///
+impl SharedSdm {
+    ///
+    /// Share `sdm`, splitting its counters into `DEFAULT_SHARDS` shards.
+    ///
+    pub fn new(sdm: SDM) -> Self {
+        SharedSdm::with_shards(sdm, DEFAULT_SHARDS)
+    }
+    ///
+    /// Share `sdm`, splitting its counters into about `shards` shards of equal size.
+    ///
+    pub fn with_shards(sdm: SDM, shards: usize) -> Self {
+        assert!(shards > 0, "at least one shard");
+        let per_shard = (ADD_DIM + shards - 1) / shards;
+        let a = sdm.address_space().clone();
+        let shards = sdm.counter().as_slice().chunks(per_shard * Counter::row()).map(|c| RwLock::new(c.to_vec())).collect();
+        SharedSdm { a, per_shard, shards }
+    }
+    ///
+    ///
+    ///
+    pub fn address_space(&self) -> &AddressSpace { &self.a }
+    ///
+    ///
+    ///
+    pub fn shards(&self) -> usize { self.shards.len() }
+    ///
+    /// Write `data` at the hard locations activated by `address`.
+    ///
+    pub fn write_data(&self, address: &BitTable, data: &BitTable, radius: usize) {
+        let selected = self.a.radius_fit(address, radius);
+        let n = Counter::row();
+        for (s, shard) in self.shards.iter().enumerate() {
+            let first = s * self.per_shard;
+            let rows = &selected[first..ADD_DIM.min(first + self.per_shard)];
+            if rows.iter().all(|&x| x == 0) { continue; }
+            let mut c = write_lock(shard);
+            for (j, _) in rows.iter().enumerate().filter(|&(_, &x)| x != 0) {
+                Counter::input_row(&mut c[j * n..(j + 1) * n], data);
+            }
+        }
+    }
+    ///
+    /// Write `b` at itself, locking one shard at a time.
+    ///
+    pub fn write(&self, b: &BitTable, radius: usize) {
+        self.write_data(b, b, radius)
+    }
+    ///
+    /// Read at `cue` under the read lock of each activated shard in turn.
+    ///
+    pub fn read(&self, cue: &BitTable, radius: usize) -> BitTable {
+        let selected = self.a.radius_fit(cue, radius);
+        let n = Counter::row();
+        let mut sum: Vec<i32> = vec![0; n];
+        for (s, shard) in self.shards.iter().enumerate() {
+            let first = s * self.per_shard;
+            let rows = &selected[first..ADD_DIM.min(first + self.per_shard)];
+            if rows.iter().all(|&x| x == 0) { continue; }
+            let c = read_lock(shard);
+            for (j, _) in rows.iter().enumerate().filter(|&(_, &x)| x != 0) {
+                for (total, &x) in sum.iter_mut().zip(c[j * n..(j + 1) * n].iter()) { *total += i32::from(x); }
+            }
+        }
+        threshold(&sum)
+    }
+    ///
+    /// Reset all the counters, shard by shard.
+    ///
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            for x in write_lock(shard).iter_mut() { *x = 0; }
+        }
+    }
+    ///
+    /// Copy of the memory as a plain `SDM`; writes running concurrently may be seen
+    /// in some shards only.
+    ///
+    pub fn to_sdm(&self) -> SDM {
+        let mut c: Vec<i16> = Vec::with_capacity(ADD_DIM * Counter::row());
+        for shard in self.shards.iter() { c.extend_from_slice(&read_lock(shard)); }
+        SDM::from_parts(self.a.clone(), Counter::from_vec(c))
+    }
+    ///
+    /// The plain `SDM`, once no other thread uses the memory.
+    ///
+    pub fn into_sdm(self) -> SDM {
+        let mut c: Vec<i16> = Vec::with_capacity(ADD_DIM * Counter::row());
+        for shard in self.shards { c.extend(shard.into_inner().unwrap_or_else(|e| e.into_inner())); }
+        SDM::from_parts(self.a, Counter::from_vec(c))
+    }
+}
+///
+/// An SDM whose writes update atomic counters without locks; share it with `Arc<AtomicSdm>`.
+///
+#[derive(Debug)]
+pub struct AtomicSdm {
+    a: AddressSpace,
+    c: AtomicCounter,
+}
+///
+///
+///
+impl AtomicSdm {
+    ///
+    /// Share `sdm` with atomic counters.
+    ///
+    pub fn new(sdm: SDM) -> Self {
+        AtomicSdm { a: sdm.address_space().clone(), c: AtomicCounter::from_counter(sdm.counter()) }
+    }
+    ///
+    ///
+    ///
+    pub fn address_space(&self) -> &AddressSpace { &self.a }
+    ///
+    ///
+    ///
+    pub fn counter(&self) -> &AtomicCounter { &self.c }
+    ///
+    /// Write `data` at the hard locations activated by `address`; counters saturate.
+    ///
+    pub fn write_data(&self, address: &BitTable, data: &BitTable, radius: usize) {
+        let selected = self.a.radius_fit(address, radius);
+        for i in (0..ADD_DIM).filter(|&i| selected[i] != 0) { self.c.input(i, data); }
+    }
+    ///
+    /// Write `b` at itself without taking a lock.
+    ///
+    pub fn write(&self, b: &BitTable, radius: usize) {
+        self.write_data(b, b, radius)
+    }
+    ///
+    /// Read at `cue`; writes running concurrently may be seen on some counters only.
+    ///
+    pub fn read(&self, cue: &BitTable, radius: usize) -> BitTable {
+        let selected = self.a.radius_fit(cue, radius);
+        let mut sum: Vec<i32> = vec![0; Counter::row()];
+        for i in (0..ADD_DIM).filter(|&i| selected[i] != 0) {
+            for (total, x) in sum.iter_mut().zip(self.c.get_counters(i)) { *total += i32::from(x); }
+        }
+        threshold(&sum)
+    }
+    ///
+    /// Reset all the counters.
+    ///
+    pub fn clear(&self) { self.c.clear(); }
+    ///
+    /// Copy of the memory as a plain `SDM`; writes running concurrently may be seen partially.
+    ///
+    pub fn to_sdm(&self) -> SDM {
+        SDM::from_parts(self.a.clone(), self.c.to_counter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use super::*;
+    use vsa;
+
+    const RADIUS: usize = 54;
+
+    fn sdm() -> SDM { SDM::from_parts(AddressSpace::with_seed(1), Counter::new()) }
+
+    #[test]
+    fn test_shared_matches_sdm() {
+        let mut plain = sdm();
+        let shared = SharedSdm::with_shards(sdm(), 7);
+        assert_eq!(shared.shards(), 7);
+        for k in 0..10 {
+            let (a, d) = (vsa::random(k), vsa::random(100 + k));
+            plain.write_data(&a, &d, RADIUS);
+            shared.write_data(&a, &d, RADIUS);
+        }
+        assert_eq!(shared.to_sdm().counter().as_slice(), plain.counter().as_slice());
+        let x = vsa::random(3);
+        assert_eq!(shared.read(&x, RADIUS), vsa::random(103));
+        shared.clear();
+        assert!(shared.into_sdm().counter().as_slice().iter().all(|&c| c == 0));
+    }
+
+    #[test]
+    fn test_shared_single_shard() {
+        let shared = SharedSdm::with_shards(sdm(), 1);
+        let x = vsa::random(4);
+        shared.write(&x, RADIUS);
+        assert_eq!(shared.read(&x, RADIUS), x);
+    }
+
+    #[test]
+    fn test_shared_atomic_matches_sdm() {
+        let mut plain = sdm();
+        let atomic = AtomicSdm::new(sdm());
+        for k in 0..10 {
+            let (a, d) = (vsa::random(k), vsa::random(100 + k));
+            plain.write_data(&a, &d, RADIUS);
+            atomic.write_data(&a, &d, RADIUS);
+        }
+        assert_eq!(atomic.to_sdm().counter().as_slice(), plain.counter().as_slice());
+        assert_eq!(atomic.read(&vsa::random(3), RADIUS), vsa::random(103));
+        atomic.clear();
+        assert!(atomic.to_sdm().counter().as_slice().iter().all(|&c| c == 0));
+    }
+
+    #[test]
+    fn test_shared_atomic_concurrent_writers() {
+        let atomic = Arc::new(AtomicSdm::new(sdm()));
+        let handles: Vec<_> = (0..8u64).map(|t| {
+            let atomic = atomic.clone();
+            thread::spawn(move || {
+                for k in 0..25 { atomic.write(&vsa::random(t * 1000 + k), RADIUS); }
+            })
+        }).collect();
+        for h in handles { h.join().unwrap(); }
+        let mut plain = sdm();
+        for t in 0..8u64 {
+            for k in 0..25 { plain.write(&vsa::random(t * 1000 + k), RADIUS); }
+        }
+        assert_eq!(atomic.to_sdm().counter().as_slice(), plain.counter().as_slice());
+    }
+
+    #[test]
+    fn test_shared_concurrent_writers() {
+        // Concurrent writes commute: the counters equal those of the sequential writes.
+        let shared = Arc::new(SharedSdm::new(sdm()));
+        let handles: Vec<_> = (0..8u64).map(|t| {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                for k in 0..25 { shared.write(&vsa::random(t * 1000 + k), RADIUS); }
+            })
+        }).collect();
+        for h in handles { h.join().unwrap(); }
+        let mut plain = sdm();
+        for t in 0..8u64 {
+            for k in 0..25 { plain.write(&vsa::random(t * 1000 + k), RADIUS); }
+        }
+        assert_eq!(shared.to_sdm().counter().as_slice(), plain.counter().as_slice());
+    }
+
+    #[test]
+    fn test_shared_readers_during_writes() {
+        // Patterns stored before the stress stay readable while other threads write.
+        let shared = Arc::new(SharedSdm::new(sdm()));
+        let stored: Vec<BitTable> = (0..5).map(|k| vsa::random(500 + k)).collect();
+        for p in stored.iter() { shared.write(p, RADIUS); }
+        let done = Arc::new(AtomicBool::new(false));
+        let writers: Vec<_> = (0..4u64).map(|t| {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                for k in 0..5 { shared.write(&vsa::random(10_000 + t * 100 + k), RADIUS); }
+            })
+        }).collect();
+        let readers: Vec<_> = (0..4).map(|_| {
+            let (shared, done, stored) = (shared.clone(), done.clone(), stored.clone());
+            thread::spawn(move || {
+                let mut reads = 0;
+                while !done.load(Ordering::SeqCst) || reads == 0 {
+                    for p in stored.iter() { assert!(shared.read(p, RADIUS).distance(p) <= 10); }
+                    reads += 1;
+                }
+                reads
+            })
+        }).collect();
+        for h in writers { h.join().unwrap(); }
+        done.store(true, Ordering::SeqCst);
+        for h in readers { assert!(h.join().unwrap() > 0); }
+    }
+}
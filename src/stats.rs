@@ -0,0 +1,222 @@
+//
+// Statistics on the state and the use of an SDM.
+//
+// An SDM records its activity as it is used: the number of writes and reads, how many
+// hard locations each one activated, and how many writes activated each hard location.
+// Reads take `&self`, so their tallies are atomic. `SDM::stats` combines the activity
+// with the current counters into a `Stats` report.
+//
+// The remaining capacity is estimated with `analysis::capacity_at`, from the activation
+// probability measured on the writes and a signal-to-noise ratio of `MIN_SNR` at a
+// written address.
+//
+#[allow(missing_docs)]
+include!(concat!(env!("OUT_DIR"), "/dimensions.rs"));
+///
+use std::collections::BTreeMap;
+///
+use std::i16;
+///
+use std::sync::atomic::{AtomicUsize, Ordering};
+///
+use analysis;
+///
+use counter::Counter;
+///
+/// Signal-to-noise ratio at a written address below which a memory is overloaded.
+///
+pub const MIN_SNR: f64 = 3.0;
+///
+/// Report on the state and the use of a memory.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stats {
+    ///
+    /// Number of writes, erasures included.
+    ///
+    pub writes: u64,
+    ///
+    /// Number of reads.
+    ///
+    pub reads: u64,
+    ///
+    /// Pairs `(writes, locations)`: number of hard locations activated by that many
+    /// writes, by increasing number of writes.
+    ///
+    pub activations: Vec<(u64, usize)>,
+    ///
+    /// Fraction of the counters at the bounds of `i16`.
+    ///
+    pub saturated: f64,
+    ///
+    /// Pairs `(value, counters)`: number of counters with that value, by increasing value.
+    ///
+    pub counters: Vec<(i16, usize)>,
+    ///
+    /// Mean number of hard locations activated by a write or a read.
+    ///
+    pub mean_activated: f64,
+    ///
+    /// Estimated number of writes before a read at a written address falls below
+    /// `MIN_SNR`, or `None` before the first write.
+    ///
+    pub remaining_capacity: Option<u64>,
+}
+///
+///
+///
+impl Stats {
+    ///
+    /// Whether the memory holds more writes than its estimated capacity.
+    ///
+    pub fn is_overloaded(&self) -> bool { self.remaining_capacity == Some(0) }
+}
+///
+/// Remaining capacity after `writes`, each activating hard locations with probability `p`.
+///
+pub(crate) fn remaining_capacity(p: f64, writes: u64) -> u64 {
+    (analysis::capacity_at(ADD_DIM, p, MIN_SNR) as u64).saturating_sub(writes)
+}
+///
+/// Activity of an SDM since its creation or last `clear`.
+///
+#[derive(Debug, Default)]
+pub(crate) struct Activity {
+    writes: u64,
+    write_activated: u64,
+    locations: Vec<u64>,
+    reads: AtomicUsize,
+    read_activated: AtomicUsize,
+}
+///
+///
+///
+impl Clone for Activity {
+    fn clone(&self) -> Self {
+        Activity {
+            writes: self.writes,
+            write_activated: self.write_activated,
+            locations: self.locations.clone(),
+            reads: AtomicUsize::new(self.reads.load(Ordering::Relaxed)),
+            read_activated: AtomicUsize::new(self.read_activated.load(Ordering::Relaxed)),
+        }
+    }
+}
+///
+///
+///
+impl Activity {
+    ///
+    ///
+    ///
+    pub(crate) fn new() -> Self { Activity { locations: vec![0; ADD_DIM], ..Activity::default() } }
+    ///
+    /// Record a write activating the hard locations where `selected` is not `0`.
+    ///
+    pub(crate) fn write(&mut self, selected: &[usize]) {
+        self.writes += 1;
+        for (n, _) in self.locations.iter_mut().zip(selected.iter()).filter(|&(_, &s)| s != 0) {
+            *n += 1;
+            self.write_activated += 1;
+        }
+    }
+    ///
+    /// Record a read activating `activated` hard locations.
+    ///
+    pub(crate) fn read(&self, activated: usize) {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.read_activated.fetch_add(activated, Ordering::Relaxed);
+    }
+    ///
+    /// Add the activity of a memory merged into this one.
+    ///
+    pub(crate) fn merge(&mut self, other: &Activity) {
+        self.writes += other.writes;
+        self.write_activated += other.write_activated;
+        for (n, &m) in self.locations.iter_mut().zip(other.locations.iter()) { *n += m; }
+        self.reads.fetch_add(other.reads.load(Ordering::Relaxed), Ordering::Relaxed);
+        self.read_activated.fetch_add(other.read_activated.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+    ///
+    /// Report on this activity and the counters `c`.
+    ///
+    pub(crate) fn stats(&self, c: &Counter) -> Stats {
+        let mut activations: BTreeMap<u64, usize> = BTreeMap::new();
+        for &n in self.locations.iter() { *activations.entry(n).or_insert(0) += 1; }
+        let mut counters: BTreeMap<i16, usize> = BTreeMap::new();
+        for &x in c.as_slice() { *counters.entry(x).or_insert(0) += 1; }
+        let saturated = counters.get(&i16::MIN).cloned().unwrap_or(0) + counters.get(&i16::MAX).cloned().unwrap_or(0);
+        let reads = self.reads.load(Ordering::Relaxed) as u64;
+        let operations = self.writes + reads;
+        let activated = self.write_activated + self.read_activated.load(Ordering::Relaxed) as u64;
+        let remaining_capacity = if self.writes == 0 { None } else {
+            let p = self.write_activated as f64 / (self.writes as f64 * ADD_DIM as f64);
+            Some(remaining_capacity(p, self.writes))
+        };
+        Stats {
+            writes: self.writes,
+            reads,
+            activations: activations.into_iter().collect(),
+            saturated: if c.len() == 0 { 0.0 } else { saturated as f64 / c.len() as f64 },
+            counters: counters.into_iter().collect(),
+            mean_activated: if operations == 0 { 0.0 } else { activated as f64 / operations as f64 },
+            remaining_capacity,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use address_space::AddressSpace;
+    use bittable::BitTable;
+    use vsa;
+    use SDM;
+
+    const RADIUS: usize = 54;
+
+    fn sdm_activated(sdm: &SDM, x: &BitTable) -> usize {
+        sdm.scan(x, RADIUS).iter().filter(|&&s| s != 0).count()
+    }
+
+    #[test]
+    fn test_stats_activity() {
+        let mut sdm = SDM::from_parts(AddressSpace::with_seed(4), Counter::new());
+        let empty = sdm.stats();
+        assert_eq!((empty.writes, empty.reads, empty.remaining_capacity), (0, 0, None));
+        assert_eq!(empty.activations, vec![(0, ADD_DIM)]);
+        assert_eq!(empty.counters, vec![(0, sdm.counter().len())]);
+        let written: usize = (0..4).map(|k| sdm_activated(&sdm, &vsa::random(k))).sum();
+        for k in 0..4 { sdm.write(&vsa::random(k), RADIUS); }
+        sdm.read(&vsa::random(0), RADIUS);
+        let s = sdm.stats();
+        assert_eq!((s.writes, s.reads), (4, 1));
+        assert_eq!(s.activations.iter().map(|&(_, n)| n).sum::<usize>(), ADD_DIM);
+        assert_eq!(s.activations.iter().map(|&(w, n)| w as usize * n).sum::<usize>(), written);
+        assert_eq!(s.counters.iter().map(|&(_, n)| n).sum::<usize>(), sdm.counter().len());
+        assert!((s.mean_activated - (written + sdm_activated(&sdm, &vsa::random(0))) as f64 / 5.0).abs() < 1e-9);
+        assert_eq!(s.saturated, 0.0);
+        let remaining = s.remaining_capacity.unwrap();
+        assert!(remaining > 0 && !s.is_overloaded());
+        sdm.write(&vsa::random(9), RADIUS);
+        assert!(sdm.stats().remaining_capacity.unwrap() <= remaining);
+        sdm.clear();
+        assert_eq!(sdm.stats(), empty);
+    }
+
+    #[test]
+    fn test_stats_saturated() {
+        // At radius 0 a hard location written at its own address is the only one
+        // activated: one write more than `i16::MAX` saturates its whole row.
+        let mut sdm = SDM::from_parts(AddressSpace::with_seed(4), Counter::new());
+        let x = sdm.address_space().get(0).clone();
+        for _ in 0..(i16::MAX as usize) + 1 { sdm.write(&x, 0); }
+        let n = x.len();
+        let s = sdm.stats();
+        assert_eq!(s.writes, i16::MAX as u64 + 1);
+        assert_eq!(s.saturated, n as f64 / (n * ADD_DIM) as f64);
+        assert_eq!(s.counters.first(), Some(&(i16::MIN, n - x.ones())));
+        assert_eq!(s.counters.last(), Some(&(i16::MAX, x.ones())));
+    }
+}
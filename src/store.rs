@@ -0,0 +1,519 @@
+//
+// Native persisted memory: one file holding the address space, the counters and the
+// parameters needed to use the memory again.
+//
+// The layout follows the `interop` files, a text header closed by an empty line and
+// little-endian binary data:
+//
+//     KANERVA MEMORY
+//     Format-Version: 1
+//     Format: binary
+//     Order-of-bytes: little-endian
+//     Bits: <bits>
+//     Sample: <sample>               (number of hard locations)
+//     Counter-Size: 4
+//     Seed: <seed>                   (of the address space)
+//     Radius: <radius>               (activation radius)
+//     Writes: <writes>               (number of patterns written)
+//     Projection: <0 or 1>
+//     Log-Sequence: <sequence>       (last `wal` record included, optional)
+//
+//     <sample addresses, as `interop` address words>
+//     <sample * bits signed 32-bit counters>
+//     <the `SimHashEncoder` projection file, if `Projection` is 1>
+//
+#[allow(missing_docs)]
+include!(concat!(env!("OUT_DIR"), "/dimensions.rs"));
+///
+use std::fs::{self, File};
+///
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+///
+use std::path::Path;
+///
+use std::process;
+///
+use std::sync::atomic::{AtomicUsize, Ordering};
+///
+use bittable::BitTable;
+///
+use address_space::AddressSpace;
+///
+use analysis;
+///
+use counter::Counter;
+///
+use encoder::simhash::SimHashEncoder;
+///
+use merge::{same, MergeError};
+///
+use interop::{expect_field, from_words, invalid, parse_field, read_header, read_i32, read_u64, to_words, write_i32, write_u64};
+///
+use stats::{remaining_capacity, Stats};
+///
+use SDM;
+///
+/// Version of the memory file layout.
+///
+pub const FORMAT_VERSION: u64 = 1;
+///
+/// Number of hard locations of the memories of this build.
+///
+pub fn hard_locations() -> usize { ADD_DIM }
+///
+/// Parameters of a memory file.
+///
+#[derive(Debug)]
+struct Header {
+    seed: u64,
+    radius: usize,
+    writes: u64,
+    projection: bool,
+    sequence: u64,
+}
+///
+/// Read and check the header of a memory file.
+///
+fn read_memory_header<R: BufRead>(r: &mut R) -> io::Result<Header> {
+    let header = read_header(r, "KANERVA MEMORY")?;
+    let version = parse_field(&header, "Format-Version")?;
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(invalid(format!("unsupported memory format version {}", version)));
+    }
+    expect_field(&header, "Bits", BitTable::new().len())?;
+    expect_field(&header, "Sample", ADD_DIM)?;
+    expect_field(&header, "Counter-Size", 4)?;
+    let projection = match parse_field(&header, "Projection")? {
+        0 => false,
+        1 => true,
+        x => return Err(invalid(format!("`Projection` is {}, expected 0 or 1", x))),
+    };
+    Ok(Header {
+        seed: parse_field(&header, "Seed")?,
+        radius: parse_field(&header, "Radius")? as usize,
+        writes: parse_field(&header, "Writes")?,
+        projection,
+        sequence: if header.contains_key("Log-Sequence") { parse_field(&header, "Log-Sequence")? } else { 0 },
+    })
+}
+///
+/// Read the address of one hard location.
+///
+fn read_address<R: BufRead>(r: &mut R) -> io::Result<BitTable> {
+    let mut words: Vec<u64> = vec![0; to_words(&BitTable::new()).len()];
+    for w in words.iter_mut() { *w = read_u64(r)?; }
+    Ok(from_words(&words, BitTable::new().len()))
+}
+///
+/// Read the counters of one hard location.
+///
+fn read_row<R: BufRead>(r: &mut R) -> io::Result<Vec<i16>> {
+    let mut row: Vec<i16> = Vec::with_capacity(BitTable::new().len());
+    for _ in 0..BitTable::new().len() {
+        let x = read_i32(r)?;
+        if x < i32::from(i16::min_value()) || x > i32::from(i16::max_value()) {
+            return Err(invalid(format!("counter {} out of range", x)));
+        }
+        row.push(x as i16);
+    }
+    Ok(row)
+}
+///
+/// Check that two memories have the same projection.
+///
+fn same_projection(left: Option<&SimHashEncoder>, right: Option<&SimHashEncoder>) -> Result<(), MergeError> {
+    if left == right { return Ok(()); }
+    let describe = |p: Option<&SimHashEncoder>| match p {
+        Some(p) => format!("{} dims, seed {}, version {}", p.dims(), p.seed(), p.version()),
+        None => "none".to_string(),
+    };
+    Err(MergeError::Parameter { name: "projection", left: describe(left), right: describe(right) })
+}
+///
+/// An SDM with its seed, activation radius, write count and optional embedding projection.
+///
+#[derive(Clone, Debug)]
+pub struct Memory {
+    sdm: SDM,
+    seed: u64,
+    radius: usize,
+    writes: u64,
+    projection: Option<SimHashEncoder>,
+    sequence: u64,
+}
+///
+///
+///
+impl Memory {
+    ///
+    /// An empty memory with hard locations drawn from `seed`.
+    ///
+    pub fn new(seed: u64, radius: usize) -> Self {
+        let sdm = SDM::from_parts(AddressSpace::with_seed(seed), Counter::new());
+        Memory { sdm, seed, radius, writes: 0, projection: None, sequence: 0 }
+    }
+    ///
+    ///
+    ///
+    pub fn sdm(&self) -> &SDM { &self.sdm }
+    ///
+    ///
+    ///
+    pub fn sdm_mut(&mut self) -> &mut SDM { &mut self.sdm }
+    ///
+    ///
+    ///
+    pub fn seed(&self) -> u64 { self.seed }
+    ///
+    ///
+    ///
+    pub fn radius(&self) -> usize { self.radius }
+    ///
+    ///
+    ///
+    pub fn set_radius(&mut self, radius: usize) { self.radius = radius; }
+    ///
+    /// Number of patterns written through `write`.
+    ///
+    pub fn writes(&self) -> u64 { self.writes }
+    ///
+    /// The projection of embeddings into addresses, if any.
+    ///
+    pub fn projection(&self) -> Option<&SimHashEncoder> { self.projection.as_ref() }
+    ///
+    /// Attach the projection of embeddings into addresses; it is saved with the memory.
+    ///
+    pub fn set_projection(&mut self, projection: SimHashEncoder) { self.projection = Some(projection); }
+    ///
+    /// Sequence number of the last write-ahead log record included in the memory.
+    ///
+    pub fn log_sequence(&self) -> u64 { self.sequence }
+    ///
+    ///
+    ///
+    pub(crate) fn set_log_sequence(&mut self, sequence: u64) { self.sequence = sequence; }
+    ///
+    /// Write `data` at `address` with the memory radius.
+    ///
+    pub fn write(&mut self, address: &BitTable, data: &BitTable) {
+        self.sdm.write_data(address, data, self.radius);
+        self.writes += 1;
+    }
+    ///
+    /// Write `data` at `address` `weight` times.
+    ///
+    pub fn write_weighted(&mut self, address: &BitTable, data: &BitTable, weight: u32) {
+        for _ in 0..weight { self.write(address, data); }
+    }
+    ///
+    /// Write a stream of `(address, data)` pairs, such as a `PatternReader`, and
+    /// return their number.
+    ///
+    /// Stops at the first error; the pairs before it stay written.
+    ///
+    pub fn write_batch<I, E>(&mut self, patterns: I) -> Result<usize, E>
+        where I: IntoIterator<Item = Result<(BitTable, BitTable), E>>
+    {
+        let mut count: usize = 0;
+        for p in patterns {
+            let (address, data) = p?;
+            self.write(&address, &data);
+            count += 1;
+        }
+        Ok(count)
+    }
+    ///
+    /// Undo a previous `write(address, data)` by writing the complement of `data`.
+    ///
+    pub fn erase(&mut self, address: &BitTable, data: &BitTable) {
+        let ones: Vec<bool> = vec![true; data.len()];
+        let complement = data.clone() ^ BitTable::from_slice(&ones);
+        self.sdm.write_data(address, &complement, self.radius);
+        self.writes = self.writes.saturating_sub(1);
+    }
+    ///
+    /// Undo a previous `write_weighted(address, data, weight)`.
+    ///
+    pub fn erase_weighted(&mut self, address: &BitTable, data: &BitTable, weight: u32) {
+        for _ in 0..weight { self.erase(address, data); }
+    }
+    ///
+    /// Erase everything written, keeping the hard locations.
+    ///
+    pub fn clear(&mut self) {
+        self.sdm.clear();
+        self.writes = 0;
+    }
+    ///
+    /// Read at `cue` with the memory radius.
+    ///
+    pub fn read(&self, cue: &BitTable) -> BitTable {
+        self.sdm.read(cue, self.radius)
+    }
+    ///
+    /// Read iteratively from `cue` with the memory radius, as `SDM::recall`.
+    ///
+    pub fn recall(&self, cue: &BitTable, steps: usize) -> (BitTable, usize) {
+        self.sdm.recall(cue, self.radius, steps)
+    }
+    ///
+    /// Statistics of the memory as `SDM::stats`, but with the number of writes kept in
+    /// the file, erasures deducted, and the capacity predicted from the radius.
+    ///
+    /// The activations and reads only cover the writes and reads since loading.
+    ///
+    pub fn stats(&self) -> Stats {
+        let mut s = self.sdm.stats();
+        let p = analysis::activation_probability(BitTable::new().len(), self.radius);
+        s.writes = self.writes;
+        s.remaining_capacity = Some(remaining_capacity(p, self.writes));
+        s
+    }
+    ///
+    /// Serialize the memory.
+    ///
+    pub fn save_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let bits = BitTable::new().len();
+        let a = self.sdm.address_space();
+        write!(w, "KANERVA MEMORY\n")?;
+        write!(w, "Format-Version: {}\n", FORMAT_VERSION)?;
+        write!(w, "Format: binary\n")?;
+        write!(w, "Order-of-bytes: little-endian\n")?;
+        write!(w, "Bits: {}\n", bits)?;
+        write!(w, "Sample: {}\n", a.len())?;
+        write!(w, "Counter-Size: 4\n")?;
+        write!(w, "Seed: {}\n", self.seed)?;
+        write!(w, "Radius: {}\n", self.radius)?;
+        write!(w, "Writes: {}\n", self.writes)?;
+        write!(w, "Projection: {}\n", if self.projection.is_some() { 1 } else { 0 })?;
+        write!(w, "Log-Sequence: {}\n", self.sequence)?;
+        write!(w, "\n")?;
+        for i in 0..a.len() {
+            for word in to_words(a.get(i)) { write_u64(w, word)?; }
+        }
+        for &x in self.sdm.counter().as_slice() { write_i32(w, i32::from(x))?; }
+        if let Some(ref p) = self.projection { p.write(w)?; }
+        Ok(())
+    }
+    ///
+    /// Deserialize a memory written by `save_to`.
+    ///
+    pub fn load_from<R: BufRead>(r: &mut R) -> io::Result<Self> {
+        let h = read_memory_header(r)?;
+        let mut vec: Vec<BitTable> = Vec::with_capacity(ADD_DIM);
+        for _ in 0..ADD_DIM { vec.push(read_address(r)?); }
+        let mut c: Vec<i16> = Vec::with_capacity(BitTable::new().len() * ADD_DIM);
+        for _ in 0..ADD_DIM { c.extend(read_row(r)?); }
+        let projection = if h.projection { Some(SimHashEncoder::read(r)?) } else { None };
+        let sdm = SDM::from_parts(AddressSpace::from_vec(vec), Counter::from_vec(c));
+        Ok(Memory { sdm, seed: h.seed, radius: h.radius, writes: h.writes, projection, sequence: h.sequence })
+    }
+    ///
+    /// Add the counters of `other`, which must have the same seed, radius, hard locations
+    /// and projection. Returns the number of counters that saturated.
+    ///
+    pub fn merge(&mut self, other: &Memory) -> Result<usize, MergeError> {
+        same("seed", self.seed, other.seed)?;
+        same("radius", self.radius, other.radius)?;
+        same_projection(self.projection(), other.projection())?;
+        let saturated = self.sdm.merge(&other.sdm)?;
+        self.writes += other.writes;
+        Ok(saturated)
+    }
+    ///
+    /// Merge the memory serialized in `r` without loading it whole: the hard locations
+    /// are compared and the counters added as they are read.
+    ///
+    /// The parameters and hard locations are checked before any counter is added, but
+    /// a read error or a different projection, stored last, leaves the memory partly
+    /// merged: discard it then.
+    ///
+    pub fn merge_from<R: BufRead>(&mut self, r: &mut R) -> Result<usize, MergeError> {
+        let h = read_memory_header(r)?;
+        same("seed", self.seed, h.seed)?;
+        same("radius", self.radius, h.radius)?;
+        same("projection", self.projection.is_some(), h.projection)?;
+        for i in 0..ADD_DIM {
+            if &read_address(r)? != self.sdm.address_space().get(i) {
+                return Err(MergeError::AddressSpace { location: i });
+            }
+        }
+        let mut saturated = 0;
+        for i in 0..ADD_DIM { saturated += self.sdm.counter_mut().merge_row(i, &read_row(r)?); }
+        if h.projection {
+            let other = SimHashEncoder::read(r)?;
+            same_projection(self.projection(), Some(&other))?;
+        }
+        self.writes += h.writes;
+        Ok(saturated)
+    }
+    ///
+    /// Save the memory to `path`, replacing the file only once it is completely written.
+    ///
+    /// The file is written under a temporary name unique to the process and the call, so
+    /// that concurrent saves to the same path do not write into the same file; it is
+    /// removed if the save fails.
+    ///
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        static SAVES: AtomicUsize = AtomicUsize::new(0);
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(format!(".{}.{}.tmp", process::id(), SAVES.fetch_add(1, Ordering::Relaxed)));
+        let saved = File::create(&tmp).and_then(|f| {
+            let mut w = BufWriter::new(f);
+            self.save_to(&mut w)?;
+            w.flush()?;
+            w.get_ref().sync_all()
+        }).and_then(|_| fs::rename(&tmp, path));
+        if saved.is_err() { let _ = fs::remove_file(&tmp); }
+        saved
+    }
+    ///
+    /// Load a memory saved by `save`.
+    ///
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Memory::load_from(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env;
+    use std::fs;
+    use std::io::{Cursor, ErrorKind};
+    use super::*;
+    use vsa;
+
+    #[test]
+    fn test_store_seeded() {
+        let a = Memory::new(1, 54);
+        let b = Memory::new(1, 54);
+        assert_eq!(a.sdm().address_space().get(7), b.sdm().address_space().get(7));
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        let mut m = Memory::new(2, 54);
+        m.set_projection(SimHashEncoder::new(3, 2));
+        m.set_log_sequence(7);
+        let x = vsa::random(10);
+        m.write_weighted(&x, &x, 2);
+        let mut buf: Vec<u8> = Vec::new();
+        m.save_to(&mut buf).unwrap();
+        let n = Memory::load_from(&mut Cursor::new(buf)).unwrap();
+        assert_eq!((n.seed(), n.radius(), n.writes(), n.log_sequence()), (2, 54, 2, 7));
+        assert_eq!(n.sdm().counter().as_slice(), m.sdm().counter().as_slice());
+        assert_eq!(n.projection(), m.projection());
+        assert_eq!(n.read(&x), x);
+    }
+
+    #[test]
+    fn test_store_save_load_file() {
+        let path = env::temp_dir().join(format!("kanerva-store-{}.sdm", process::id()));
+        let mut m = Memory::new(3, 54);
+        let x = vsa::random(11);
+        m.write(&x, &x);
+        m.save(&path).unwrap();
+        let n = Memory::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(n.writes(), 1);
+        assert_eq!(n.stats().writes, 1);
+        assert_eq!(n.stats().remaining_capacity, m.stats().remaining_capacity);
+        assert!(n.projection().is_none());
+        assert_eq!(n.sdm().counter().as_slice(), m.sdm().counter().as_slice());
+    }
+
+    #[test]
+    fn test_store_save_failure() {
+        // Renaming onto a directory fails: the temporary file must not be left behind.
+        let dir = env::temp_dir().join(format!("kanerva-store-failure-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("m.sdm")).unwrap();
+        assert!(Memory::new(4, 54).save(dir.join("m.sdm")).is_err());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_store_write_batch() {
+        let mut m = Memory::new(5, 54);
+        let (x, y) = (vsa::random(12), vsa::random(13));
+        let ok: Vec<Result<(BitTable, BitTable), String>> = vec![Ok((x.clone(), x.clone())), Ok((y.clone(), y.clone()))];
+        assert_eq!(m.write_batch(ok), Ok(2));
+        assert_eq!(m.writes(), 2);
+        let failing: Vec<Result<(BitTable, BitTable), String>> = vec![Ok((x.clone(), x.clone())), Err("bad".to_string()), Ok((y.clone(), y))];
+        assert_eq!(m.write_batch(failing), Err("bad".to_string()));
+        assert_eq!(m.writes(), 3);
+    }
+
+    #[test]
+    fn test_store_erase_clear() {
+        let mut m = Memory::new(6, 54);
+        let (x, y) = (vsa::random(14), vsa::random(15));
+        m.write(&x, &x);
+        let before: Vec<i16> = m.sdm().counter().as_slice().to_vec();
+        m.write(&y, &y);
+        m.erase(&y, &y);
+        assert_eq!(m.sdm().counter().as_slice(), &before[..]);
+        assert_eq!(m.writes(), 1);
+        assert_eq!((m.stats().writes, m.sdm().stats().writes), (1, 3));
+        m.clear();
+        assert!(m.sdm().counter().as_slice().iter().all(|&c| c == 0));
+        assert_eq!(m.writes(), 0);
+    }
+
+    #[test]
+    fn test_store_merge() {
+        let (x, y) = (vsa::random(16), vsa::random(17));
+        let mut a = Memory::new(7, 54);
+        let mut b = Memory::new(7, 54);
+        let mut both = Memory::new(7, 54);
+        a.write(&x, &x);
+        b.write(&y, &y);
+        both.write(&x, &x);
+        both.write(&y, &y);
+        let mut streamed = a.clone();
+        assert_eq!(a.merge(&b).unwrap(), 0);
+        assert_eq!(a.sdm().counter().as_slice(), both.sdm().counter().as_slice());
+        assert_eq!(a.writes(), 2);
+        let mut buf: Vec<u8> = Vec::new();
+        b.save_to(&mut buf).unwrap();
+        assert_eq!(streamed.merge_from(&mut Cursor::new(buf)).unwrap(), 0);
+        assert_eq!(streamed.sdm().counter().as_slice(), both.sdm().counter().as_slice());
+    }
+
+    #[test]
+    fn test_store_merge_mismatch() {
+        let mut a = Memory::new(8, 54);
+        match a.merge(&Memory::new(9, 54)) {
+            Err(MergeError::Parameter { name, .. }) => assert_eq!(name, "seed"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(a.merge(&Memory::new(8, 50)).is_err());
+        let mut other = a.sdm().clone();
+        let mut sdm = SDM::from_parts(AddressSpace::with_seed(9), Counter::new());
+        match other.merge(&sdm) {
+            Err(MergeError::AddressSpace { location }) => assert_eq!(location, 0),
+            r => panic!("unexpected {:?}", r),
+        }
+        assert!(sdm.merge(&other).is_err());
+        let mut p = Memory::new(8, 54);
+        p.set_projection(SimHashEncoder::new(3, 1));
+        let mut buf: Vec<u8> = Vec::new();
+        p.save_to(&mut buf).unwrap();
+        assert!(a.merge(&p).is_err());
+        assert!(a.merge_from(&mut Cursor::new(buf)).is_err());
+    }
+
+    #[test]
+    fn test_store_errors() {
+        let mut buf: Vec<u8> = Vec::new();
+        Memory::new(4, 54).save_to(&mut buf).unwrap();
+        buf.truncate(buf.len() - 1);
+        let e = Memory::load_from(&mut Cursor::new(buf)).err().unwrap();
+        assert_eq!(e.kind(), ErrorKind::UnexpectedEof);
+        let e = Memory::load_from(&mut Cursor::new(b"SDM COUNTER\n\n".to_vec())).err().unwrap();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+    }
+}
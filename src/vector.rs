@@ -1,20 +1,19 @@
+//
+// Bit-vector of `DIM` words, wrapping a `BitTable`.
+//
 #[allow(missing_docs)]
 include!(concat!(env!("OUT_DIR"), "/dimensions.rs"));
 ///
-use rand::distributions::Bernoulli;
-///
-use rand::{thread_rng, Rng};
-///
 use std::mem;
 ///
 use std::fmt;
 ///
-use std::ptr;
-///
 use std::ops::{BitXor, BitAnd, BitOr};
 ///
 use bittable::BitTable;
 ///
+use hypervector::HyperVector;
+///
 /// Number of bits: in a `usize` (`64` in a 64 bits target).
 ///
 #[inline(always)]
@@ -44,12 +43,18 @@ impl Clone for Vector {
 ///
 ///
 ///
+impl PartialEq for Vector {
+    fn eq(&self, other: &Vector) -> bool { self.0 == other.0 }
+}
+///
+///
+///
 impl Vector {
 	///
 	/// 
 	///
 	pub fn new() -> Self {
-		Vector(BitTable::new_rand(SDM_DIM))
+		Vector(BitTable::new_rand())
 	}
 	///
 	/// 
@@ -61,7 +66,7 @@ impl Vector {
 	/// 
 	///
 	pub fn new_from_slice(s: &[bool]) -> Self {
-		assert_eq!(s.len(), SDM_DIM);
+		assert_eq!(s.len(), DIM*bits());
 		let bt=BitTable::from_slice(s);
 		Vector(bt)
 	}
@@ -71,7 +76,7 @@ impl Vector {
 	/// A copy into a new `Vec<bool>` is needed.
 	///
 	pub fn new_from_vec(s: &Vec<bool>) -> Self {
-		assert_eq!(s.len(), SDM_DIM*bits());
+		assert_eq!(s.len(), DIM*bits());
 		let bt=BitTable::from_slice(s.as_slice());
 		Vector(bt)
 	}
@@ -130,9 +135,7 @@ impl BitXor for Vector {
     fn bitxor(self, Vector(rhs): Self) -> Self {
         let Vector(lhs) = self;
         assert_eq!(lhs.len(), rhs.len());
-        let xor = lhs^rhs;
-        println!("xor.len() = {:?}", xor.len());
-        Vector(xor)
+        Vector(lhs^rhs)
     }
 }
 ///
@@ -171,6 +174,24 @@ impl BitOr for Vector {
         Vector(lhs|rhs)
     }
 }
+///
+///
+///
+impl HyperVector for Vector {
+    fn random(len: usize) -> Self {
+        assert_eq!(len, DIM*bits(), "a Vector has {} bits", DIM*bits());
+        Vector::new()
+    }
+    fn from_bools(bits: &[bool]) -> Self { Vector::new_from_slice(bits) }
+    fn len(&self) -> usize { self.0.len() }
+    fn get(&self, i: usize) -> bool { self.0.get(i) }
+    fn set(&mut self, i: usize, value: bool) { HyperVector::set(&mut self.0, i, value) }
+    fn distance(&self, other: &Self) -> usize { self.0.distance(&other.0) }
+    fn xor(&self, other: &Self) -> Self { self.clone() ^ other.clone() }
+    fn and(&self, other: &Self) -> Self { self.clone() & other.clone() }
+    fn or(&self, other: &Self) -> Self { self.clone() | other.clone() }
+    fn popcount(&self) -> usize { HyperVector::popcount(&self.0) }
+}
 
 #[cfg(test)]
 mod tests {
@@ -178,7 +199,8 @@ mod tests {
 	use std::mem;
     use super::Vector;
     use super::bits;
-    use super::SDM_DIM;
+    use super::DIM;
+    use hypervector::tests::check;
 
     #[test]
     fn test_vector_size() {
@@ -188,14 +210,14 @@ mod tests {
     #[test]
     fn test_vector_new() {
         let a: Vector = Vector::new();
-        assert_eq!(a.len(), SDM_DIM*bits());
+        assert_eq!(a.len(), DIM*bits());
     } 
 
     #[test]
     fn test_vector_xor() {
         let a: Vector = Vector::new();
         let al = a.len();
-        assert_eq!(al, SDM_DIM*bits());
+        assert_eq!(al, DIM*bits());
         //a.show();
         let b: Vector = Vector::new();
         let bl = b.len();
@@ -206,4 +228,9 @@ mod tests {
         //c.show();
         assert_eq!(cl, al);
     } 
+
+    #[test]
+    fn test_vector_hypervector() {
+        check::<Vector>(DIM*bits());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,176 @@
+//
+// Binary spatter code algebra for hyperdimensional computing (Kanerva, 2009).
+//
+//   bind:       `a ^ b`, self-inverse, preserves distances.
+//   bundle:     bitwise majority, similar to each of its inputs.
+//   permute:    cyclic shift, quasi-orthogonal to its input.
+//   similarity: `1 - distance / len`, `0.5` for unrelated vectors.
+//
+// The operations work on any `HyperVector`; `random` draws `BitTable`s, to be
+// converted with `hypervector::convert` for the other representations.
+//
+///
+use rand::Rng;
+///
+use bittable::BitTable;
+///
+use hypervector::HyperVector;
+///
+use noise::rng_from_seed;
+///
+/// A seeded random hypervector.
+///
+pub fn random(seed: u64) -> BitTable {
+    let mut rng = rng_from_seed(seed);
+    let bits: Vec<bool> = (0..BitTable::new().len()).map(|_| rng.gen()).collect();
+    BitTable::from_slice(&bits)
+}
+///
+/// Bind two hypervectors (XOR).
+///
+/// `bind(&bind(a, b), b) == a`.
+///
+pub fn bind<V: HyperVector>(a: &V, b: &V) -> V {
+    assert_eq!(a.len(), b.len());
+    a.xor(b)
+}
+///
+/// Bundle hypervectors by bitwise majority.
+///
+/// With an even number of inputs, ties are broken by a coin flip seeded with `seed`.
+///
+pub fn bundle<V: HyperVector>(vs: &[V], seed: u64) -> V {
+    assert!(!vs.is_empty(), "cannot bundle an empty set");
+    let n = vs[0].len();
+    let mut rng = rng_from_seed(seed);
+    let mut counts: Vec<usize> = vec![0; n];
+    for v in vs.iter() {
+        assert_eq!(v.len(), n);
+        for i in 0..n { if v.get(i) { counts[i] += 1; } }
+    }
+    let bits: Vec<bool> = counts.iter().map(|&c| {
+        if 2*c > vs.len() { true }
+        else if 2*c < vs.len() { false }
+        else { rng.gen() }
+    }).collect();
+    V::from_bools(&bits)
+}
+///
+/// Cyclic shift of `k` positions: bit `i` moves to `(i + k) % len`.
+///
+pub fn permute<V: HyperVector>(a: &V, k: usize) -> V {
+    let n = a.len();
+    let mut bits: Vec<bool> = a.to_bools();
+    bits.rotate_right(k % n);
+    V::from_bools(&bits)
+}
+///
+/// Inverse of `permute(_, k)`.
+///
+pub fn inverse_permute<V: HyperVector>(a: &V, k: usize) -> V {
+    let n = a.len();
+    let mut bits: Vec<bool> = a.to_bools();
+    bits.rotate_left(k % n);
+    V::from_bools(&bits)
+}
+///
+/// Normalized Hamming similarity, in `[0, 1]`.
+///
+pub fn similarity<V: HyperVector>(a: &V, b: &V) -> f64 {
+    assert_eq!(a.len(), b.len());
+    1.0 - (a.distance(b) as f64) / (a.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const TRIALS: u64 = 20;
+
+    #[test]
+    fn test_vsa_random_seeded() {
+        assert_eq!(random(1), random(1));
+        assert!(random(1) != random(2));
+    }
+
+    #[test]
+    fn test_vsa_bind_self_inverse() {
+        for s in 0..TRIALS {
+            let a = random(2*s);
+            let b = random(2*s+1);
+            assert_eq!(bind(&bind(&a, &b), &b), a);
+            assert_eq!(bind(&a, &b), bind(&b, &a));
+        }
+    }
+
+    #[test]
+    fn test_vsa_bind_preserves_distance() {
+        for s in 0..TRIALS {
+            let (a, b, c) = (random(3*s), random(3*s+1), random(3*s+2));
+            assert_eq!(bind(&a, &c).distance(&bind(&b, &c)), a.distance(&b));
+        }
+    }
+
+    #[test]
+    fn test_vsa_bundle_similarity_bounds() {
+        for s in 0..TRIALS {
+            let vs: Vec<BitTable> = (0..3).map(|k| random(10*s+k)).collect();
+            let b = bundle(&vs, s);
+            // Each input agrees with the majority of three on about 3/4 of the bits.
+            for v in vs.iter() {
+                let sim = similarity(&b, v);
+                assert!(sim > 0.6, "similarity {} too low", sim);
+            }
+            // An unrelated vector stays around 1/2.
+            let sim = similarity(&b, &random(10*s+9));
+            assert!(sim > 0.3 && sim < 0.7, "similarity {} to unrelated", sim);
+        }
+    }
+
+    #[test]
+    fn test_vsa_bundle_ties_seeded() {
+        let vs = vec![random(1), random(2)];
+        assert_eq!(bundle(&vs, 5), bundle(&vs, 5));
+        assert_eq!(bundle(&vs[..1], 5), vs[0]);
+    }
+
+    #[test]
+    fn test_vsa_permute() {
+        for s in 0..TRIALS {
+            let a = random(s);
+            let k = (s as usize) * 7 + 1;
+            assert_eq!(inverse_permute(&permute(&a, k), k), a);
+            assert_eq!(permute(&a, a.len()), a);
+            let sim = similarity(&a, &permute(&a, k));
+            assert!(sim > 0.3 && sim < 0.7, "permutation similarity {}", sim);
+            // Permutation distributes over binding.
+            let b = random(s + 100);
+            assert_eq!(permute(&bind(&a, &b), k), bind(&permute(&a, k), &permute(&b, k)));
+        }
+    }
+
+    #[test]
+    fn test_vsa_hypervector() {
+        use bitstring::Bitstring;
+        use hypervector::convert;
+        use vector::Vector;
+        let (a, b, c) = (random(1), random(2), random(3));
+        let bundled = bundle(&[a.clone(), b.clone(), c.clone()], 4);
+        let (va, vb, vc): (Vector, Vector, Vector) = (convert(&a), convert(&b), convert(&c));
+        assert_eq!(bind(&va, &vb), convert(&bind(&a, &b)));
+        assert_eq!(permute(&va, 5), convert(&permute(&a, 5)));
+        assert_eq!(bundle(&[va.clone(), vb, vc], 4), convert(&bundled));
+        let (sa, sb): (Bitstring, Bitstring) = (convert(&a), convert(&b));
+        assert_eq!(inverse_permute(&permute(&sa, 9), 9), sa);
+        assert_eq!(similarity(&sa, &sb), similarity(&a, &b));
+    }
+
+    #[test]
+    fn test_vsa_similarity() {
+        let a = random(0);
+        assert_eq!(similarity(&a, &a), 1.0);
+        let na: Vec<bool> = a.iter().map(|b| !b).collect();
+        assert_eq!(similarity(&a, &BitTable::from_slice(&na)), 0.0);
+    }
+}
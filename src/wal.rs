@@ -0,0 +1,491 @@
+//
+// Write-ahead log of a persisted memory.
+//
+// A `LoggedMemory` at `<path>` is the `store` snapshot `<path>` and the log `<path>.log`.
+// Every `write` and `erase` is appended to the log, and synced by default, before it is
+// applied; `compact` saves a new snapshot and empties the log. `open` loads the snapshot
+// and replays the log records it does not include yet.
+//
+// The log has an `interop`-style text header and little-endian binary records:
+//
+//     KANERVA LOG
+//     Format-Version: 1
+//     Format: binary
+//     Order-of-bytes: little-endian
+//     Bits: <bits>
+//     Seed: <seed>                   (of the address space of the snapshot)
+//
+//     <sequence: u64> <kind: u32> <weight: u32> <address words> <data words> <checksum: u32>
+//
+// `kind` is 1 for a write and 2 for an erase; the checksum is the FNV-1a hash of the
+// bytes of the record before it. Sequence numbers increase by one from record to record,
+// and the snapshot header keeps the last one it includes (`Log-Sequence`): a crash
+// between saving the snapshot and emptying the log replays nothing twice. A record
+// torn by a crash fails its checksum and is cut off the log on `open`.
+//
+// No random state is logged: the hard locations are fixed by the seed of the header,
+// and a write or an erase only adds `1` or `-1` to the counters of the locations its
+// address activates, so replaying the records gives the same counters. The random
+// tie-breaking of `threshold` happens on reads, which change no counter and are not
+// logged.
+//
+///
+use std::fs::{self, File, OpenOptions};
+///
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+///
+use std::path::{Path, PathBuf};
+///
+use bittable::BitTable;
+///
+use interop::{expect_field, from_words, invalid, parse_field, read_header, to_words};
+///
+use store::Memory;
+///
+/// Version of the log layout.
+///
+pub const LOG_VERSION: u64 = 1;
+///
+/// Default number of log records after which `LoggedMemory` compacts.
+///
+pub const DEFAULT_COMPACT_EVERY: u64 = 10_000;
+///
+///
+///
+const WRITE: u32 = 1;
+///
+///
+///
+const ERASE: u32 = 2;
+///
+/// A `Memory` whose changes are logged before they are applied.
+///
+#[derive(Debug)]
+pub struct LoggedMemory {
+    memory: Memory,
+    path: PathBuf,
+    log: File,
+    log_len: u64,
+    records: u64,
+    sync: bool,
+    compact_every: u64,
+    compact_error: Option<io::Error>,
+}
+///
+/// Path of the log of the snapshot `path`.
+///
+pub fn log_path(path: &Path) -> PathBuf {
+    let mut p = path.as_os_str().to_owned();
+    p.push(".log");
+    PathBuf::from(p)
+}
+///
+/// 32-bit FNV-1a hash.
+///
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5u32, |h, &b| (h ^ u32::from(b)).wrapping_mul(0x0100_0193))
+}
+///
+///
+///
+fn put_u32(buf: &mut Vec<u8>, x: u32) {
+    for k in 0..4 { buf.push((x >> (8 * k)) as u8); }
+}
+///
+///
+///
+fn put_u64(buf: &mut Vec<u8>, x: u64) {
+    for k in 0..8 { buf.push((x >> (8 * k)) as u8); }
+}
+///
+///
+///
+fn get_u32(bytes: &[u8]) -> u32 {
+    bytes[..4].iter().rev().fold(0u32, |x, &b| (x << 8) | u32::from(b))
+}
+///
+///
+///
+fn get_u64(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0u64, |x, &b| (x << 8) | u64::from(b))
+}
+///
+/// Number of bytes of a record.
+///
+fn record_len() -> usize { 8 + 4 + 4 + 2 * 8 * to_words(&BitTable::new()).len() + 4 }
+///
+/// Bytes of a record, checksum included.
+///
+fn encode(sequence: u64, kind: u32, weight: u32, address: &BitTable, data: &BitTable) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::with_capacity(record_len());
+    put_u64(&mut buf, sequence);
+    put_u32(&mut buf, kind);
+    put_u32(&mut buf, weight);
+    for w in to_words(address).into_iter().chain(to_words(data)) { put_u64(&mut buf, w); }
+    let checksum = fnv1a(&buf);
+    put_u32(&mut buf, checksum);
+    buf
+}
+///
+/// A record read back from the log.
+///
+#[derive(Debug)]
+struct Record {
+    sequence: u64,
+    kind: u32,
+    weight: u32,
+    address: BitTable,
+    data: BitTable,
+}
+///
+/// Decode a record, or `None` if its checksum or kind is wrong.
+///
+fn decode(buf: &[u8]) -> Option<Record> {
+    let n = buf.len() - 4;
+    if fnv1a(&buf[..n]) != get_u32(&buf[n..]) { return None; }
+    let bits = BitTable::new().len();
+    let words: Vec<u64> = buf[16..n].chunks(8).map(get_u64).collect();
+    let half = words.len() / 2;
+    let kind = get_u32(&buf[8..12]);
+    if kind != WRITE && kind != ERASE { return None; }
+    Some(Record {
+        sequence: get_u64(&buf[..8]),
+        kind,
+        weight: get_u32(&buf[12..16]),
+        address: from_words(&words[..half], bits),
+        data: from_words(&words[half..], bits),
+    })
+}
+///
+/// Header of a new log for `memory`.
+///
+fn header(memory: &Memory) -> String {
+    format!("KANERVA LOG\nFormat-Version: {}\nFormat: binary\nOrder-of-bytes: little-endian\nBits: {}\nSeed: {}\n\n",
+        LOG_VERSION, BitTable::new().len(), memory.seed())
+}
+///
+/// Replace the log of `path` by an empty log for `memory`.
+///
+fn reset_log(path: &Path, memory: &Memory) -> io::Result<File> {
+    let log = log_path(path);
+    let mut tmp = log.as_os_str().to_owned();
+    tmp.push(".tmp");
+    {
+        let mut f = File::create(&tmp)?;
+        f.write_all(header(memory).as_bytes())?;
+        f.sync_all()?;
+    }
+    fs::rename(&tmp, &log)?;
+    OpenOptions::new().append(true).open(&log)
+}
+///
+/// Apply the records of the log `r` not included in `memory`, returning the number of
+/// bytes of valid log and the number of records it holds.
+///
+fn replay<R: BufRead + Seek>(r: &mut R, memory: &mut Memory) -> io::Result<(u64, u64)> {
+    let header = read_header(r, "KANERVA LOG")?;
+    let version = parse_field(&header, "Format-Version")?;
+    if version == 0 || version > LOG_VERSION {
+        return Err(invalid(format!("unsupported log format version {}", version)));
+    }
+    expect_field(&header, "Bits", BitTable::new().len())?;
+    if parse_field(&header, "Seed")? != memory.seed() {
+        return Err(invalid("the log does not belong to this memory (different seed)".to_string()));
+    }
+    let mut valid = r.seek(SeekFrom::Current(0))?;
+    let mut records: u64 = 0;
+    let mut buf = vec![0u8; record_len()];
+    loop {
+        if !read_full(r, &mut buf)? { break; }
+        let record = match decode(&buf) { Some(record) => record, None => break };
+        if record.sequence > memory.log_sequence() {
+            if record.sequence != memory.log_sequence() + 1 {
+                return Err(invalid(format!("log record {} follows {}: the snapshot is older than the log",
+                    record.sequence, memory.log_sequence())));
+            }
+            match record.kind {
+                WRITE => memory.write_weighted(&record.address, &record.data, record.weight),
+                _ => memory.erase_weighted(&record.address, &record.data, record.weight),
+            }
+            memory.set_log_sequence(record.sequence);
+        }
+        records += 1;
+        valid += buf.len() as u64;
+    }
+    Ok((valid, records))
+}
+///
+/// Fill `buf`, returning `false` at the end of the input or on a truncated record.
+///
+fn read_full<R: Read>(r: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match r.read(&mut buf[filled..]) {
+            Ok(0) => return Ok(false),
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+///
+///
+///
+impl LoggedMemory {
+    ///
+    /// Save `memory` as a new snapshot at `path` with an empty log.
+    ///
+    pub fn create<P: AsRef<Path>>(path: P, mut memory: Memory) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        memory.set_log_sequence(0);
+        memory.save(&path)?;
+        LoggedMemory::with_empty_log(path, memory)
+    }
+    ///
+    /// Recover the memory at `path`: load the snapshot and replay its log, cutting off a
+    /// record torn by a crash. A missing log is created empty.
+    ///
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut memory = Memory::load(&path)?;
+        let log_file = log_path(&path);
+        if !log_file.exists() {
+            return LoggedMemory::with_empty_log(path, memory);
+        }
+        let (valid, records) = {
+            let mut r = BufReader::new(File::open(&log_file)?);
+            replay(&mut r, &mut memory)?
+        };
+        let mut log = OpenOptions::new().write(true).open(&log_file)?;
+        if log.metadata()?.len() > valid {
+            log.set_len(valid)?;
+            log.sync_all()?;
+        }
+        log.seek(SeekFrom::End(0))?;
+        Ok(LoggedMemory { memory, path, log, log_len: valid, records, sync: true, compact_every: DEFAULT_COMPACT_EVERY,
+            compact_error: None })
+    }
+    ///
+    ///
+    ///
+    fn with_empty_log(path: PathBuf, memory: Memory) -> io::Result<Self> {
+        let log = reset_log(&path, &memory)?;
+        let log_len = log.metadata()?.len();
+        Ok(LoggedMemory { memory, path, log, log_len, records: 0, sync: true, compact_every: DEFAULT_COMPACT_EVERY,
+            compact_error: None })
+    }
+    ///
+    ///
+    ///
+    pub fn memory(&self) -> &Memory { &self.memory }
+    ///
+    ///
+    ///
+    pub fn path(&self) -> &Path { &self.path }
+    ///
+    /// Number of records in the log since the last compaction.
+    ///
+    pub fn log_records(&self) -> u64 { self.records }
+    ///
+    /// Whether every record is synced to disk before it is applied (the default).
+    /// Without it, `sync` bounds what a crash can lose.
+    ///
+    pub fn set_sync(&mut self, sync: bool) { self.sync = sync; }
+    ///
+    /// Compact after `records` log records; `0` compacts only on `compact`.
+    ///
+    pub fn set_compact_every(&mut self, records: u64) { self.compact_every = records; }
+    ///
+    /// Error of the last automatic compaction, if it failed and no compaction succeeded
+    /// since. The write that triggered it was logged and applied all the same, and the
+    /// compaction is tried again after the next write.
+    ///
+    pub fn compact_error(&self) -> Option<&io::Error> { self.compact_error.as_ref() }
+    ///
+    /// Log and apply a write of `data` at `address`, `weight` times.
+    ///
+    /// An error means that nothing was written: the write may be retried. A failed
+    /// automatic compaction is reported by `compact_error` instead.
+    ///
+    pub fn write(&mut self, address: &BitTable, data: &BitTable, weight: u32) -> io::Result<()> {
+        self.append(WRITE, address, data, weight)?;
+        self.memory.write_weighted(address, data, weight);
+        self.after_append();
+        Ok(())
+    }
+    ///
+    /// Log and apply an erase of `data` at `address`, `weight` times; errors as `write`.
+    ///
+    pub fn erase(&mut self, address: &BitTable, data: &BitTable, weight: u32) -> io::Result<()> {
+        self.append(ERASE, address, data, weight)?;
+        self.memory.erase_weighted(address, data, weight);
+        self.after_append();
+        Ok(())
+    }
+    ///
+    /// Read at `cue` with the memory radius.
+    ///
+    pub fn read(&self, cue: &BitTable) -> BitTable { self.memory.read(cue) }
+    ///
+    /// Sync the log to disk.
+    ///
+    pub fn sync(&mut self) -> io::Result<()> { self.log.sync_data() }
+    ///
+    /// Save the memory as the new snapshot and empty the log.
+    ///
+    pub fn compact(&mut self) -> io::Result<()> {
+        self.memory.save(&self.path)?;
+        self.log = reset_log(&self.path, &self.memory)?;
+        self.log_len = self.log.metadata()?.len();
+        self.records = 0;
+        self.compact_error = None;
+        Ok(())
+    }
+    ///
+    ///
+    ///
+    fn append(&mut self, kind: u32, address: &BitTable, data: &BitTable, weight: u32) -> io::Result<()> {
+        let sequence = self.memory.log_sequence() + 1;
+        let record = encode(sequence, kind, weight, address, data);
+        let written = self.log.write_all(&record).and_then(|_| if self.sync { self.log.sync_data() } else { Ok(()) });
+        if let Err(e) = written {
+            // Cut a partial record off, so that the records appended later stay readable.
+            let _ = self.log.set_len(self.log_len);
+            return Err(e);
+        }
+        self.log_len += record.len() as u64;
+        self.memory.set_log_sequence(sequence);
+        self.records += 1;
+        Ok(())
+    }
+    ///
+    /// Compact if the log is due, keeping a failure for `compact_error`.
+    ///
+    fn after_append(&mut self) {
+        if self.compact_every > 0 && self.records >= self.compact_every {
+            if let Err(e) = self.compact() { self.compact_error = Some(e); }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env;
+    use std::fs::{self, OpenOptions};
+    use std::io::{ErrorKind, Write};
+    use std::path::PathBuf;
+    use std::process;
+    use super::*;
+    use vsa;
+
+    fn scratch(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("kanerva-wal-{}-{}.sdm", name, process::id()));
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(log_path(&path));
+        path
+    }
+
+    fn cleanup(path: &PathBuf) {
+        fs::remove_file(path).unwrap();
+        fs::remove_file(log_path(path)).unwrap();
+    }
+
+    #[test]
+    fn test_wal_replay() {
+        let path = scratch("replay");
+        let mut m = LoggedMemory::create(&path, Memory::new(1, 54)).unwrap();
+        let (x, y) = (vsa::random(1), vsa::random(2));
+        m.write(&x, &x, 2).unwrap();
+        m.write(&y, &y, 1).unwrap();
+        m.erase(&y, &y, 1).unwrap();
+        let expected: Vec<i16> = m.memory().sdm().counter().as_slice().to_vec();
+        drop(m);
+        // The snapshot holds no write: everything comes back from the log.
+        assert_eq!(Memory::load(&path).unwrap().writes(), 0);
+        let m = LoggedMemory::open(&path).unwrap();
+        assert_eq!(m.log_records(), 3);
+        assert_eq!(m.memory().writes(), 2);
+        assert_eq!(m.memory().sdm().counter().as_slice(), &expected[..]);
+        assert_eq!(m.read(&x), x);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_wal_torn_record() {
+        let path = scratch("torn");
+        let mut m = LoggedMemory::create(&path, Memory::new(2, 54)).unwrap();
+        let x = vsa::random(3);
+        m.write(&x, &x, 1).unwrap();
+        drop(m);
+        let len = fs::metadata(log_path(&path)).unwrap().len();
+        OpenOptions::new().append(true).open(log_path(&path)).unwrap().write_all(&[7u8; 20]).unwrap();
+        let mut m = LoggedMemory::open(&path).unwrap();
+        assert_eq!(m.memory().writes(), 1);
+        assert_eq!(fs::metadata(log_path(&path)).unwrap().len(), len);
+        let y = vsa::random(4);
+        m.write(&y, &y, 1).unwrap();
+        drop(m);
+        let m = LoggedMemory::open(&path).unwrap();
+        assert_eq!((m.log_records(), m.memory().writes()), (2, 2));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_wal_compaction() {
+        let path = scratch("compact");
+        let mut m = LoggedMemory::create(&path, Memory::new(3, 54)).unwrap();
+        m.set_compact_every(3);
+        for k in 0..5 { let x = vsa::random(10 + k); m.write(&x, &x, 1).unwrap(); }
+        assert_eq!(m.log_records(), 2);
+        let snapshot = Memory::load(&path).unwrap();
+        assert_eq!((snapshot.writes(), snapshot.log_sequence()), (3, 3));
+        // A crash after saving a snapshot, before emptying the log: nothing is replayed twice.
+        m.memory().save(&path).unwrap();
+        let expected: Vec<i16> = m.memory().sdm().counter().as_slice().to_vec();
+        drop(m);
+        let m = LoggedMemory::open(&path).unwrap();
+        assert_eq!(m.memory().writes(), 5);
+        assert_eq!(m.memory().sdm().counter().as_slice(), &expected[..]);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_wal_compaction_failure() {
+        let path = scratch("compact-failure");
+        let mut m = LoggedMemory::create(&path, Memory::new(6, 54)).unwrap();
+        m.set_compact_every(1);
+        // A directory in place of the snapshot makes the compaction fail.
+        fs::remove_file(&path).unwrap();
+        fs::create_dir(&path).unwrap();
+        let x = vsa::random(6);
+        m.write(&x, &x, 1).unwrap();
+        assert!(m.compact_error().is_some());
+        assert_eq!((m.memory().writes(), m.log_records()), (1, 1));
+        fs::remove_dir(&path).unwrap();
+        m.write(&x, &x, 1).unwrap();
+        assert!(m.compact_error().is_none());
+        assert_eq!((Memory::load(&path).unwrap().writes(), m.log_records()), (2, 0));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_wal_errors() {
+        let path = scratch("errors");
+        let mut m = LoggedMemory::create(&path, Memory::new(4, 54)).unwrap();
+        let x = vsa::random(5);
+        m.write(&x, &x, 1).unwrap();
+        m.write(&x, &x, 1).unwrap();
+        drop(m);
+        // A log whose first record is missing: the snapshot is older than the log.
+        let log = fs::read(log_path(&path)).unwrap();
+        let second = log.len() - record_len();
+        fs::write(log_path(&path), &[&log[..second - record_len()], &log[second..]].concat()).unwrap();
+        assert_eq!(LoggedMemory::open(&path).err().unwrap().kind(), ErrorKind::InvalidData);
+        // A log of another memory.
+        Memory::new(5, 54).save(&path).unwrap();
+        assert_eq!(LoggedMemory::open(&path).err().unwrap().kind(), ErrorKind::InvalidData);
+        cleanup(&path);
+    }
+}
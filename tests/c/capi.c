@@ -0,0 +1,55 @@
+/*
+ * Test program of the C API, built and run by `tests/capi.rs`.
+ *
+ *     capi <scratch file>
+ *
+ * Prints the failed check and exits with 1 on failure.
+ */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "kanerva.h"
+
+#define CHECK(cond) do { if (!(cond)) { fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__, #cond); return 1; } } while (0)
+
+int main(int argc, char **argv) {
+    size_t n = kanerva_address_bytes(), i;
+    uint8_t *x, *out;
+    kanerva_sdm *sdm, *loaded = NULL;
+
+    CHECK(argc == 2);
+    CHECK(n * 8 >= kanerva_address_bits());
+    CHECK(kanerva_hard_locations() > 0);
+
+    x = malloc(n);
+    out = malloc(n);
+    for (i = 0; i < n; i++) x[i] = (uint8_t)(i * 37 + 11);
+
+    sdm = kanerva_sdm_new(7, 0);
+    CHECK(sdm != NULL);
+    CHECK(kanerva_sdm_radius(sdm) > 0);
+    CHECK(kanerva_sdm_write(sdm, x, x, n) == KANERVA_OK);
+    CHECK(kanerva_sdm_read(sdm, x, out, n) == KANERVA_OK);
+    CHECK(memcmp(x, out, n) == 0);
+
+    CHECK(kanerva_sdm_write(sdm, x, x, n - 1) == KANERVA_ERR_LENGTH);
+    CHECK(kanerva_sdm_read(NULL, x, out, n) == KANERVA_ERR_NULL);
+    CHECK(strcmp(kanerva_strerror(KANERVA_ERR_NULL), "null pointer") == 0);
+
+    CHECK(kanerva_sdm_save(sdm, argv[1]) == KANERVA_OK);
+    CHECK(kanerva_sdm_load(argv[1], &loaded) == KANERVA_OK);
+    memset(out, 0, n);
+    CHECK(kanerva_sdm_read(loaded, x, out, n) == KANERVA_OK);
+    CHECK(memcmp(x, out, n) == 0);
+    kanerva_sdm_free(loaded);
+    CHECK(kanerva_sdm_load("/nonexistent/kanerva.sdm", &loaded) == KANERVA_ERR_IO);
+    CHECK(loaded == NULL);
+
+    kanerva_sdm_free(sdm);
+    kanerva_sdm_free(NULL);
+    free(x);
+    free(out);
+    printf("ok\n");
+    return 0;
+}
@@ -0,0 +1,59 @@
+//
+// Checks that `include/kanerva.h` is the header generated from `src/capi.rs` by
+// `build.rs`, and builds `tests/c/capi.c` against the staticlib with the system C
+// compiler and runs it. After changing `src/capi.rs`, update the header with:
+//
+//     KANERVA_BLESS=1 cargo test --test capi
+//
+// `cargo test` does not build the staticlib, so the C program is ignored by default:
+//
+//     cargo build && cargo test --test capi -- --ignored
+//
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn root() -> PathBuf { PathBuf::from(env!("CARGO_MANIFEST_DIR")) }
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+}
+
+#[test]
+fn test_capi_header_generated() {
+    let generated = read(Path::new(concat!(env!("OUT_DIR"), "/kanerva.h")));
+    assert!(generated.contains("kanerva_sdm *kanerva_sdm_new(uint64_t seed, size_t radius);"));
+    let committed = root().join("include/kanerva.h");
+    if env::var_os("KANERVA_BLESS").is_some() {
+        fs::write(&committed, &generated).unwrap_or_else(|e| panic!("{}: {}", committed.display(), e));
+    }
+    assert!(read(&committed) == generated,
+        "include/kanerva.h is not the header generated from src/capi.rs; rerun with KANERVA_BLESS=1");
+}
+
+#[test]
+#[ignore]
+fn test_capi_c_program() {
+    // The staticlib sits next to the test executable's `deps` directory.
+    let exe = env::current_exe().unwrap();
+    let lib = exe.parent().and_then(Path::parent).unwrap().join("libkanerva.a");
+    assert!(lib.exists(), "{} not built: run `cargo build` first", lib.display());
+    let out = env::temp_dir().join(format!("kanerva-capi-{}", std::process::id()));
+    let compiled = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .arg("-std=c99")
+        .arg("-I").arg(root().join("include"))
+        .arg(root().join("tests/c/capi.c"))
+        .arg(&lib)
+        .args(&["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&out)
+        .status();
+    let compiled = compiled.unwrap_or_else(|e| panic!("no C compiler: {}", e));
+    assert!(compiled.success(), "failed to compile tests/c/capi.c");
+    let scratch = env::temp_dir().join(format!("kanerva-capi-{}.sdm", std::process::id()));
+    let run = Command::new(&out).arg(&scratch).output().unwrap();
+    let _ = fs::remove_file(&out);
+    let _ = fs::remove_file(&scratch);
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "ok\n");
+}
@@ -0,0 +1,92 @@
+//
+// Runs the `kanerva` binary through init, write, read, stats and merge on memory files
+// in a scratch directory.
+//
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+const BITS: usize = 128;
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("kanerva-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn kanerva(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_kanerva")).args(args).output().unwrap()
+}
+
+fn ok(args: &[&str]) -> String {
+    let out = kanerva(args);
+    assert!(out.status.success(), "kanerva {:?}: {}", args, String::from_utf8_lossy(&out.stderr));
+    String::from_utf8(out.stdout).unwrap()
+}
+
+fn fails(args: &[&str]) -> String {
+    let out = kanerva(args);
+    assert!(!out.status.success(), "kanerva {:?} succeeded", args);
+    String::from_utf8(out.stderr).unwrap()
+}
+
+/// Pattern `k` in the `bits` format.
+fn pattern(k: usize) -> String {
+    (0..BITS).map(|i| if (i * (2 * k + 3) + k) % 7 < 3 { '1' } else { '0' }).collect()
+}
+
+fn stat(stats: &str, key: &str) -> String {
+    let line = stats.lines().find(|l| l.starts_with(key)).unwrap_or_else(|| panic!("`{}` missing", key));
+    line[key.len()..].trim_start_matches(':').trim().to_string()
+}
+
+#[test]
+fn test_cli_init_write_read_stats() {
+    let dir = scratch("memory");
+    let memory = dir.join("m.sdm");
+    let m = memory.to_str().unwrap();
+    let patterns = dir.join("p.txt");
+    let p = patterns.to_str().unwrap();
+    let lines: Vec<String> = (0..3).map(pattern).collect();
+    fs::write(&patterns, lines.join("\n") + "\n").unwrap();
+
+    assert!(ok(&["init", m, "--seed", "1", "--radius", "54"]).contains(&format!("{} bits", BITS)));
+    assert!(fails(&["init", m]).contains("already exists"));
+    assert!(fails(&["init", dir.join("n.sdm").to_str().unwrap(), "--bits", "64"]).contains("unknown option"));
+
+    assert!(ok(&["write", m, p, "--format", "bits"]).contains("wrote 3 patterns"));
+    let read = ok(&["read", m, p, "--format", "bits"]);
+    let read: Vec<&str> = read.lines().map(|l| l.split_whitespace().next().unwrap()).collect();
+    assert_eq!(read, lines.iter().map(|l| l.as_str()).collect::<Vec<&str>>());
+
+    let stats = ok(&["stats", m]);
+    assert_eq!(stat(&stats, "bits"), BITS.to_string());
+    assert_eq!(stat(&stats, "seed"), "1");
+    assert_eq!(stat(&stats, "radius"), "54");
+    assert_eq!(stat(&stats, "writes"), "3");
+    assert!(fails(&["stats", dir.join("missing.sdm").to_str().unwrap()]).contains("missing.sdm"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_cli_merge() {
+    let dir = scratch("merge");
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    for (k, name) in ["a", "b"].iter().enumerate() {
+        fs::write(path(&format!("{}.txt", name)), pattern(k) + "\n").unwrap();
+        ok(&["init", &path(&format!("{}.sdm", name)), "--seed", "2", "--radius", "54"]);
+        ok(&["write", &path(&format!("{}.sdm", name)), &path(&format!("{}.txt", name)), "--format", "bits"]);
+    }
+    ok(&["init", &path("c.sdm"), "--seed", "3", "--radius", "54"]);
+
+    assert!(ok(&["merge", &path("ab.sdm"), &path("a.sdm"), &path("b.sdm")]).contains("merged 2 memories, 2 writes"));
+    assert_eq!(stat(&ok(&["stats", &path("ab.sdm")]), "writes"), "2");
+    let read = ok(&["read", &path("ab.sdm"), &path("b.txt"), "--format", "bits"]);
+    assert_eq!(read.split_whitespace().next(), Some(pattern(1).as_str()));
+
+    assert!(fails(&["merge", &path("ab.sdm"), &path("a.sdm")]).contains("already exists"));
+    fails(&["merge", &path("ac.sdm"), &path("a.sdm"), &path("c.sdm")]);
+    let _ = fs::remove_dir_all(&dir);
+}